@@ -0,0 +1,194 @@
+// Constant-product math for YES/NO outcome-token reserves
+//
+// Every token of collateral in the vault backs one complete set (one YES and
+// one NO share). Trades mint or burn complete sets against the pool and keep
+// yes_reserve * no_reserve from decreasing. All quantities are in collateral
+// base units.
+
+// Floor integer square root
+pub fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let mut x = value;
+    let mut y = x / 2 + (x & 1);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+// Shares of one side received for `collateral_in` (after fees); None for an empty trade
+pub fn shares_out(reserve_side: u64, reserve_other: u64, collateral_in: u64) -> Option<u64> {
+    if collateral_in == 0 {
+        return None;
+    }
+    let k = reserve_side as u128 * reserve_other as u128;
+    let side_after_mint = reserve_side as u128 + collateral_in as u128;
+    let other_after_mint = reserve_other as u128 + collateral_in as u128;
+
+    // Round the side left in the pool up so k never decreases
+    let side_after_swap = (k + other_after_mint - 1) / other_after_mint;
+    u64::try_from(side_after_mint.checked_sub(side_after_swap)?).ok()
+}
+
+// Collateral received for selling `shares_in` of one side (before fees); None for an empty trade
+//
+// Solves (reserve_side + shares_in - r) * (reserve_other - r) = k for r.
+pub fn collateral_out(reserve_side: u64, reserve_other: u64, shares_in: u64) -> Option<u64> {
+    if shares_in == 0 {
+        return None;
+    }
+    let sum = reserve_side as u128 + shares_in as u128 + reserve_other as u128;
+    let discriminant = sum
+        .checked_mul(sum)?
+        .checked_sub(4 * shares_in as u128 * reserve_other as u128)?;
+
+    // Round the square root up so the payout rounds down
+    let mut root = isqrt(discriminant);
+    if root * root < discriminant {
+        root += 1;
+    }
+
+    u64::try_from(sum.checked_sub(root)? / 2).ok()
+}
+
+// Amount of each side added to the reserves for a deposit, plus LP shares minted
+//
+// Deposits keep the current reserve ratio; outcome shares that don't fit the
+// ratio are returned to the provider.
+pub fn deposit(
+    reserve_yes: u64,
+    reserve_no: u64,
+    lp_supply: u64,
+    amount: u64,
+) -> Option<(u64, u64, u64)> {
+    if lp_supply == 0 {
+        return Some((amount, amount, amount));
+    }
+
+    let pool_weight = reserve_yes.max(reserve_no) as u128;
+    if pool_weight == 0 {
+        return None;
+    }
+    let yes_added = amount as u128 * reserve_yes as u128 / pool_weight;
+    let no_added = amount as u128 * reserve_no as u128 / pool_weight;
+    let lp_minted = amount as u128 * lp_supply as u128 / pool_weight;
+
+    Some((
+        u64::try_from(yes_added).ok()?,
+        u64::try_from(no_added).ok()?,
+        u64::try_from(lp_minted).ok()?,
+    ))
+}
+
+// Share of each reserve owed for burning `lp_shares`
+pub fn withdraw(reserve_yes: u64, reserve_no: u64, lp_supply: u64, lp_shares: u64) -> Option<(u64, u64)> {
+    if lp_supply == 0 {
+        return None;
+    }
+    let yes_out = reserve_yes as u128 * lp_shares as u128 / lp_supply as u128;
+    let no_out = reserve_no as u128 * lp_shares as u128 / lp_supply as u128;
+
+    Some((u64::try_from(yes_out).ok()?, u64::try_from(no_out).ok()?))
+}
+
+// YES price in basis points implied by the reserves
+pub fn price_yes_bps(reserve_yes: u64, reserve_no: u64) -> u16 {
+    let total = reserve_yes as u128 + reserve_no as u128;
+    if total == 0 {
+        return 5000;
+    }
+    (reserve_no as u128 * 10000 / total) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEE_BPS: u128 = 30;
+
+    fn k(reserve_yes: u64, reserve_no: u64) -> u128 {
+        reserve_yes as u128 * reserve_no as u128
+    }
+
+    // Reserves after buying YES, as swap_collateral_for_shares updates them
+    fn buy_yes(reserve_yes: u64, reserve_no: u64, collateral_in: u64) -> (u64, u64, u64) {
+        let fee = (collateral_in as u128 * FEE_BPS / 10000) as u64;
+        let minted = collateral_in - fee;
+        let shares = shares_out(reserve_yes, reserve_no, minted).unwrap();
+        (reserve_yes + minted - shares + fee, reserve_no + minted + fee, shares)
+    }
+
+    // Reserves after selling YES, as swap_shares_for_collateral updates them
+    fn sell_yes(reserve_yes: u64, reserve_no: u64, shares_in: u64) -> (u64, u64, u64) {
+        let burned = collateral_out(reserve_yes, reserve_no, shares_in).unwrap();
+        let fee = (burned as u128 * FEE_BPS / 10000) as u64;
+        (reserve_yes + shares_in - burned + fee, reserve_no - burned + fee, burned - fee)
+    }
+
+    #[test]
+    fn isqrt_is_floor() {
+        for value in [0u128, 1, 2, 3, 4, 15, 16, 17, 1 << 64, u128::MAX] {
+            let root = isqrt(value);
+            assert!(root * root <= value);
+            assert!((root + 1).checked_mul(root + 1).map_or(true, |square| square > value));
+        }
+    }
+
+    #[test]
+    fn swaps_with_fee_never_decrease_k() {
+        let reserves = [(1_000_000, 1_000_000), (3_000_000, 250_000), (7, 1_000_000_000)];
+        for (reserve_yes, reserve_no) in reserves {
+            for amount in [1, 333, 10_000, 5_000_000] {
+                let (yes_after, no_after, _) = buy_yes(reserve_yes, reserve_no, amount);
+                assert!(k(yes_after, no_after) >= k(reserve_yes, reserve_no));
+
+                let (yes_after, no_after, _) = sell_yes(reserve_yes, reserve_no, amount);
+                assert!(k(yes_after, no_after) >= k(reserve_yes, reserve_no));
+            }
+        }
+    }
+
+    #[test]
+    fn rounding_favours_the_pool() {
+        let (reserve_yes, reserve_no) = (1_000_003u64, 999_983u64);
+        for amount in [1u64, 17, 4_999, 123_457] {
+            // Exact: shares = y + a - y * n / (n + a)
+            let exact_shares = reserve_yes as f64 + amount as f64
+                - reserve_yes as f64 * reserve_no as f64 / (reserve_no as f64 + amount as f64);
+            assert!(shares_out(reserve_yes, reserve_no, amount).unwrap() as f64 <= exact_shares);
+
+            // Selling straight back returns no more than was paid
+            let shares = shares_out(reserve_yes, reserve_no, amount).unwrap();
+            let yes_after = reserve_yes + amount - shares;
+            let no_after = reserve_no + amount;
+            if shares > 0 {
+                assert!(collateral_out(yes_after, no_after, shares).unwrap() <= amount);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_and_oversized_swaps_are_rejected() {
+        assert_eq!(shares_out(1_000_000, 1_000_000, 0), None);
+        assert_eq!(collateral_out(1_000_000, 1_000_000, 0), None);
+
+        // The buyer would be owed more shares than fit in a u64
+        assert_eq!(shares_out(u64::MAX / 2, u64::MAX / 2, u64::MAX), None);
+        // The discriminant overflows u128
+        assert_eq!(collateral_out(u64::MAX, u64::MAX, u64::MAX), None);
+    }
+
+    #[test]
+    fn deposits_and_withdrawals_round_down() {
+        let (yes_added, no_added, lp_minted) = deposit(3_000, 1_000, 3_000, 1_001).unwrap();
+        assert_eq!((yes_added, no_added, lp_minted), (1_001, 333, 1_001));
+
+        let (yes_out, no_out) = withdraw(3_000, 1_000, 3_000, 1_001).unwrap();
+        assert!(yes_out <= 1_001 && no_out <= 333);
+        assert_eq!(withdraw(3_000, 1_000, 0, 1), None);
+        assert_eq!(deposit(0, 0, 1, 1), None);
+    }
+}
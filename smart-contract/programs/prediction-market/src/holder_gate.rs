@@ -0,0 +1,264 @@
+// Holder-only markets
+//
+// A market can require bettors to hold a minimum balance of some mint, or an
+// NFT from a verified Metaplex collection. The user supplies the token account
+// (and, for collections, the NFT's metadata account) when placing a bet.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::ErrorCode;
+
+// Metaplex Token Metadata program
+pub mod token_metadata {
+    use anchor_lang::prelude::*;
+
+    declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Default)]
+pub enum HolderGate {
+    // Anyone holding the betting token may bet
+    #[default]
+    Open,
+    // Hold at least `min_amount` base units of `mint`
+    TokenBalance { mint: Pubkey, min_amount: u64 },
+    // Hold an NFT that is a verified member of `collection`
+    NftCollection { collection: Pubkey },
+}
+
+impl HolderGate {
+    pub const INIT_SPACE: usize = 1 + 32 + 8;
+
+    // Check the accounts a bettor supplied against this gate
+    pub fn verify(
+        &self,
+        user: &Pubkey,
+        token_account: Option<&TokenAccount>,
+        metadata: Option<&AccountInfo>,
+    ) -> Result<()> {
+        match self {
+            HolderGate::Open => Ok(()),
+            HolderGate::TokenBalance { mint, min_amount } => {
+                let token_account = token_account.ok_or(ErrorCode::HolderGateNotMet)?;
+                require!(
+                    token_account.owner == *user
+                        && token_account.mint == *mint
+                        && token_account.amount >= *min_amount,
+                    ErrorCode::HolderGateNotMet
+                );
+                Ok(())
+            }
+            HolderGate::NftCollection { collection } => {
+                let token_account = token_account.ok_or(ErrorCode::HolderGateNotMet)?;
+                let metadata = metadata.ok_or(ErrorCode::HolderGateNotMet)?;
+                require!(
+                    token_account.owner == *user && token_account.amount >= 1,
+                    ErrorCode::HolderGateNotMet
+                );
+
+                let (expected, _) = Pubkey::find_program_address(
+                    &[
+                        b"metadata",
+                        token_metadata::ID.as_ref(),
+                        token_account.mint.as_ref(),
+                    ],
+                    &token_metadata::ID,
+                );
+                require!(
+                    metadata.key() == expected && *metadata.owner == token_metadata::ID,
+                    ErrorCode::HolderGateNotMet
+                );
+
+                let data = metadata.try_borrow_data()?;
+                require!(
+                    verified_collection(&data) == Some(*collection),
+                    ErrorCode::HolderGateNotMet
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+// Verified collection key from raw Metaplex metadata, if any
+//
+// Layout: key, update_authority, mint, name, symbol, uri, seller_fee_basis_points,
+// creators, primary_sale_happened, is_mutable, edition_nonce, token_standard, collection.
+fn verified_collection(data: &[u8]) -> Option<Pubkey> {
+    let mut reader = Reader { data, offset: 0 };
+    reader.skip(1 + 32 + 32)?;
+    for _ in 0..3 {
+        let len = reader.u32()? as usize;
+        reader.skip(len)?;
+    }
+    reader.skip(2)?;
+    if reader.u8()? == 1 {
+        // Creator: address, verified, share
+        let count = reader.u32()? as usize;
+        reader.skip(count.checked_mul(34)?)?;
+    }
+    reader.skip(2)?;
+    for _ in 0..2 {
+        if reader.u8()? == 1 {
+            reader.skip(1)?;
+        }
+    }
+    if reader.u8()? != 1 {
+        return None;
+    }
+    let verified = reader.u8()? == 1;
+    let key = Pubkey::try_from(reader.take(32)?).ok()?;
+    verified.then_some(key)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token::spl_token;
+
+    fn token_account(owner: Pubkey, mint: Pubkey, amount: u64) -> TokenAccount {
+        let mut data = [0u8; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        TokenAccount::try_deserialize(&mut &data[..]).unwrap()
+    }
+
+    // Raw Metaplex metadata with an optional collection (key, verified)
+    fn metadata_data(mint: Pubkey, collection: Option<(Pubkey, bool)>) -> Vec<u8> {
+        let mut data = vec![4];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        for text in ["Meme #1", "MEME", "https://example.com/1.json"] {
+            data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            data.extend_from_slice(text.as_bytes());
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.push(1); // One creator
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&[1, 100]);
+        data.extend_from_slice(&[0, 1]); // primary_sale_happened, is_mutable
+        data.extend_from_slice(&[1, 255]); // edition_nonce
+        data.extend_from_slice(&[1, 0]); // token_standard
+        match collection {
+            Some((key, verified)) => {
+                data.extend_from_slice(&[1, verified as u8]);
+                data.extend_from_slice(key.as_ref());
+            }
+            None => data.push(0),
+        }
+        data
+    }
+
+    fn metadata_key(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"metadata", token_metadata::ID.as_ref(), mint.as_ref()],
+            &token_metadata::ID,
+        )
+        .0
+    }
+
+    fn not_met() -> Result<()> {
+        Err(ErrorCode::HolderGateNotMet.into())
+    }
+
+    #[test]
+    fn token_balance_gate() {
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let gate = HolderGate::TokenBalance { mint, min_amount: 1_000 };
+
+        assert!(gate.verify(&user, Some(&token_account(user, mint, 1_000)), None).is_ok());
+        assert_eq!(gate.verify(&user, Some(&token_account(user, mint, 999)), None), not_met());
+        assert_eq!(
+            gate.verify(&user, Some(&token_account(user, Pubkey::new_unique(), 5_000)), None),
+            not_met()
+        );
+        assert_eq!(
+            gate.verify(&user, Some(&token_account(Pubkey::new_unique(), mint, 5_000)), None),
+            not_met()
+        );
+        assert_eq!(gate.verify(&user, None, None), not_met());
+    }
+
+    #[test]
+    fn nft_collection_gate() {
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let gate = HolderGate::NftCollection { collection };
+        let nft = token_account(user, mint, 1);
+
+        let check = |key: Pubkey, owner: Pubkey, mut data: Vec<u8>| {
+            let mut lamports = 0;
+            let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+            gate.verify(&user, Some(&nft), Some(&info))
+        };
+        let verified = metadata_data(mint, Some((collection, true)));
+
+        assert!(check(metadata_key(&mint), token_metadata::ID, verified.clone()).is_ok());
+
+        // Metadata not owned by the Token Metadata program
+        assert_eq!(check(metadata_key(&mint), Pubkey::new_unique(), verified.clone()), not_met());
+        // Metadata PDA of a different mint
+        let other_mint = Pubkey::new_unique();
+        assert_eq!(
+            check(metadata_key(&other_mint), token_metadata::ID, metadata_data(other_mint, Some((collection, true)))),
+            not_met()
+        );
+        // Unverified, foreign or missing collection
+        assert_eq!(
+            check(metadata_key(&mint), token_metadata::ID, metadata_data(mint, Some((collection, false)))),
+            not_met()
+        );
+        assert_eq!(
+            check(metadata_key(&mint), token_metadata::ID, metadata_data(mint, Some((Pubkey::new_unique(), true)))),
+            not_met()
+        );
+        assert_eq!(check(metadata_key(&mint), token_metadata::ID, metadata_data(mint, None)), not_met());
+        // Truncated metadata
+        assert_eq!(check(metadata_key(&mint), token_metadata::ID, verified[..100].to_vec()), not_met());
+
+        // The NFT must be held by the bettor
+        let sold = token_account(user, mint, 0);
+        let mut lamports = 0;
+        let mut data = verified;
+        let key = metadata_key(&mint);
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &token_metadata::ID, false, 0);
+        assert_eq!(gate.verify(&user, Some(&sold), Some(&info)), not_met());
+    }
+}
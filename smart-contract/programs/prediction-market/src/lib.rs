@@ -1,7 +1,18 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer, MintTo, Burn, CloseAccount};
 use anchor_spl::associated_token::AssociatedToken;
 
+pub mod cpmm;
+pub mod holder_gate;
+pub mod lmsr;
+pub mod merkle;
+pub mod metadata_document;
+pub mod order_book;
+
+use holder_gate::HolderGate;
+use order_book::{EventQueue, FillEvent, Order, OrderBook, OrderSide, MAX_MATCHES_PER_ORDER, MIN_ORDER_QUANTITY, ORDER_LOT_SIZE};
+
 declare_id!("So11111111111111111111111111111111111111112"); // Temporary program ID
 
 // Constants
@@ -9,10 +20,41 @@ pub const GLOBAL_SEED: &str = "global";
 pub const MARKET_SEED: &str = "market";
 pub const POSITION_SEED: &str = "position";
 pub const BET_SEED: &str = "bet";
+pub const LIQUIDITY_SEED: &str = "liquidity";
+pub const YES_MINT_SEED: &str = "yes_mint";
+pub const NO_MINT_SEED: &str = "no_mint";
+pub const ORDER_BOOK_SEED: &str = "order_book";
+pub const EVENT_QUEUE_SEED: &str = "event_queue";
+pub const OPEN_ORDERS_SEED: &str = "open_orders";
+pub const CREATOR_SEED: &str = "creator";
+pub const MARKET_METADATA_SEED: &str = "market_metadata";
 
 // Platform fee (2.5%)
 pub const PLATFORM_FEE_BASIS_POINTS: u16 = 250;
 
+// Penalty for exiting a position before close (10%), left in the pool for remaining bettors
+pub const EXIT_PENALTY_BASIS_POINTS: u16 = 1000;
+
+// Maximum LP fee on constant-product swaps (10%)
+pub const MAX_LP_FEE_BASIS_POINTS: u16 = 1000;
+
+// Maximum early-bettor bonus from a time-weight curve (2x weight at creation)
+pub const MAX_TIME_WEIGHT_BONUS_BPS: u16 = 10000;
+
+// Maximum number of allowlisted resolvers on the global state
+pub const MAX_RESOLVERS: usize = 10;
+
+// Maximum lengths for resolution rules / evidence strings
+pub const MAX_RULES_URI_LEN: usize = 200;
+pub const MAX_EVIDENCE_SOURCE_LEN: usize = 64;
+
+// Maximum lengths for market text (question, description and metadata URI live in MarketMetadata)
+pub const MAX_QUESTION_LEN: usize = 200;
+pub const MAX_DESCRIPTION_LEN: usize = 200;
+pub const MAX_TOKEN_SYMBOL_LEN: usize = 20;
+pub const MAX_TOKEN_NAME_LEN: usize = 50;
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
 #[program]
 pub mod prediction_market {
     use super::*;
@@ -24,12 +66,198 @@ pub mod prediction_market {
         global.market_count = 0;
         global.total_volume = 0;
         global.total_fees_collected = 0;
+        global.resolvers = Vec::new();
+        global.oracle = Pubkey::default();
+        global.subsidy_cap = 0;
+        global.subsidy_committed = 0;
+        global.default_min_bet = 0;
+        global.default_max_bet = 0;
+        global.min_side_depth = 0;
+        global.creation_fee = 0;
+        global.creator_bond = 0;
+        global.creation_mode = CreationMode::Permissionless;
+        global.insurance_fund = Pubkey::default();
         global.bump = ctx.bumps.global;
         
         msg!("Global state initialized");
         Ok(())
     }
 
+    // Add a key or council account to the resolver allowlist
+    pub fn add_resolver(ctx: Context<UpdateGlobal>, resolver: Pubkey) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+
+        require!(
+            !global.resolvers.contains(&resolver),
+            ErrorCode::ResolverAlreadyAllowed
+        );
+        require!(
+            global.resolvers.len() < MAX_RESOLVERS,
+            ErrorCode::TooManyResolvers
+        );
+
+        global.resolvers.push(resolver);
+
+        msg!("Resolver added to allowlist: {}", resolver);
+        Ok(())
+    }
+
+    // Remove a resolver from the allowlist (its markets cannot be resolved until re-added)
+    pub fn remove_resolver(ctx: Context<UpdateGlobal>, resolver: Pubkey) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+
+        let index = global
+            .resolvers
+            .iter()
+            .position(|key| *key == resolver)
+            .ok_or(ErrorCode::ResolverNotFound)?;
+        global.resolvers.remove(index);
+
+        msg!("Resolver removed from allowlist: {}", resolver);
+        Ok(())
+    }
+
+    // Set the oracle key used by "oracle only" markets
+    pub fn set_oracle(ctx: Context<UpdateGlobal>, oracle: Pubkey) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+        global.oracle = oracle;
+
+        msg!("Oracle set to: {}", oracle);
+        Ok(())
+    }
+
+    // Set the bet limits used by markets created without their own (max 0 = no limit)
+    pub fn set_default_bet_limits(ctx: Context<UpdateGlobal>, min_bet: u64, max_bet: u64) -> Result<()> {
+        require!(
+            max_bet == 0 || min_bet <= max_bet,
+            ErrorCode::InvalidBetLimits
+        );
+
+        let global = &mut ctx.accounts.global;
+        global.default_min_bet = min_bet;
+        global.default_max_bet = max_bet;
+
+        msg!("Default bet limits set to: {} - {}", min_bet, max_bet);
+        Ok(())
+    }
+
+    // Set the side depth the pool share cap measures against while a side is still shallow
+    pub fn set_min_side_depth(ctx: Context<UpdateGlobal>, min_side_depth: u64) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+        global.min_side_depth = min_side_depth;
+
+        msg!("Minimum side depth set to: {}", min_side_depth);
+        Ok(())
+    }
+
+    // Choose whether anyone can create markets or only allowlisted creators
+    pub fn set_creation_mode(ctx: Context<UpdateGlobal>, creation_mode: CreationMode) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+        global.creation_mode = creation_mode;
+
+        msg!("Market creation mode set to: {:?}", global.creation_mode);
+        Ok(())
+    }
+
+    // Allow a wallet to create markets while creation is permissioned
+    pub fn add_creator(ctx: Context<AddCreator>, creator: Pubkey) -> Result<()> {
+        let entry = &mut ctx.accounts.creator_entry;
+        entry.creator = creator;
+        entry.bump = ctx.bumps.creator_entry;
+
+        msg!("Creator added to allowlist: {}", creator);
+        Ok(())
+    }
+
+    // Remove a wallet from the creator allowlist, refunding the entry's rent to the authority
+    pub fn remove_creator(ctx: Context<RemoveCreator>) -> Result<()> {
+        msg!("Creator removed from allowlist: {}", ctx.accounts.creator_entry.creator);
+        Ok(())
+    }
+
+    // Send unclaimed winnings to an insurance fund instead of the treasury (default key = treasury)
+    pub fn set_insurance_fund(ctx: Context<UpdateGlobal>, insurance_fund: Pubkey) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+        global.insurance_fund = insurance_fund;
+
+        msg!("Insurance fund set to: {}", insurance_fund);
+        Ok(())
+    }
+
+    // Set the lamports charged to create a market: a fee paid to the treasury and a refundable bond
+    pub fn set_creation_costs(ctx: Context<UpdateGlobal>, creation_fee: u64, creator_bond: u64) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+        global.creation_fee = creation_fee;
+        global.creator_bond = creator_bond;
+
+        msg!("Creation fee set to: {} lamports, creator bond: {} lamports", creation_fee, creator_bond);
+        Ok(())
+    }
+
+    // Set the most the treasury may have committed to virtual-liquidity subsidies at once
+    pub fn set_subsidy_cap(ctx: Context<UpdateGlobal>, subsidy_cap: u64) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+        global.subsidy_cap = subsidy_cap;
+
+        msg!("Subsidy cap set to: {}", subsidy_cap);
+        Ok(())
+    }
+
+    // Move collected fees out of the treasury, keeping committed subsidies covered
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let global = &ctx.accounts.global;
+
+        require!(amount > 0, ErrorCode::NothingToWithdraw);
+        let remaining = ctx
+            .accounts
+            .treasury_token_account
+            .amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::NothingToWithdraw)?;
+        require!(
+            remaining >= global.subsidy_committed,
+            ErrorCode::TreasuryReserved
+        );
+
+        let seeds = &[GLOBAL_SEED.as_bytes(), &[global.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.treasury_token_account.to_account_info(),
+            to: ctx.accounts.destination_token_account.to_account_info(),
+            authority: global.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, amount)?;
+
+        msg!("Withdrew {} tokens from the treasury", amount);
+        Ok(())
+    }
+
+    // Move collected creation fees and slashed bonds out of the treasury, keeping it rent-exempt
+    pub fn withdraw_treasury_lamports(ctx: Context<WithdrawTreasuryLamports>, amount: u64) -> Result<()> {
+        let global = ctx.accounts.global.to_account_info();
+        let reserved = Rent::get()?.minimum_balance(global.data_len());
+
+        require!(amount > 0, ErrorCode::NothingToWithdraw);
+        require!(
+            global.lamports().saturating_sub(reserved) >= amount,
+            ErrorCode::NothingToWithdraw
+        );
+
+        **global.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        msg!("Withdrew {} lamports from the treasury", amount);
+        Ok(())
+    }
+
     // Create a new prediction market
     pub fn create_market(
         ctx: Context<CreateMarket>,
@@ -39,29 +267,216 @@ pub mod prediction_market {
         required_token_mint: Pubkey,
         required_token_symbol: String,
         required_token_name: String,
+        resolver: ResolverKind,
+        rules_hash: [u8; 32],
+        rules_uri: String,
+        freeze_window: i64,
+        time_weight: TimeWeightCurve,
+        seed_amount: u64,
+        seed_yes_bps: u16,
+        min_bet: u64,
+        max_bet: u64,
+        max_position: u64,
+        max_pool_share_bps: u16,
+        holder_gate: HolderGate,
+        allowlist_root: [u8; 32],
+        claim_window: i64,
+        bet_record_mode: BetRecordMode,
+        metadata_uri: String,
+        metadata_hash: [u8; 32],
     ) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+        let market_key = ctx.accounts.market.key();
         let global = &mut ctx.accounts.global;
 
+        require!(
+            rules_uri.len() <= MAX_RULES_URI_LEN,
+            ErrorCode::RulesUriTooLong
+        );
+        require!(question.len() <= MAX_QUESTION_LEN, ErrorCode::QuestionTooLong);
+        require!(description.len() <= MAX_DESCRIPTION_LEN, ErrorCode::DescriptionTooLong);
+        require!(
+            required_token_symbol.len() <= MAX_TOKEN_SYMBOL_LEN
+                && required_token_name.len() <= MAX_TOKEN_NAME_LEN,
+            ErrorCode::TokenNameTooLong
+        );
+
+        // An off-chain metadata document is optional, but its URI and hash come together
+        require!(
+            metadata_uri.len() <= MAX_METADATA_URI_LEN,
+            ErrorCode::MetadataUriTooLong
+        );
+        require!(
+            metadata_uri.is_empty() == (metadata_hash == [0u8; 32]),
+            ErrorCode::InvalidMetadata
+        );
+
+        // Betting freezes `freeze_window` seconds before closing_time
+        require!(
+            freeze_window >= 0,
+            ErrorCode::InvalidFreezeWindow
+        );
+
+        require!(
+            time_weight.max_bonus_bps() <= MAX_TIME_WEIGHT_BONUS_BPS,
+            ErrorCode::InvalidTimeWeight
+        );
+
+        require!(seed_yes_bps <= 10000, ErrorCode::InvalidSeedSplit);
+
+        // Zero limits fall back to the global defaults (in base units of the market's mint)
+        let min_bet = if min_bet == 0 { global.default_min_bet } else { min_bet };
+        let max_bet = if max_bet == 0 { global.default_max_bet } else { max_bet };
+        require!(
+            max_bet == 0 || min_bet <= max_bet,
+            ErrorCode::InvalidBetLimits
+        );
+
+        require!(
+            max_pool_share_bps <= 10000,
+            ErrorCode::InvalidPoolShare
+        );
+
+        // Winnings can be claimed for `claim_window` seconds after settlement (0 = forever)
+        require!(claim_window >= 0, ErrorCode::InvalidClaimWindow);
+
+        // The designated resolver must be allowlisted on the global state
+        require!(
+            global.is_allowed_resolver(&resolver),
+            ErrorCode::ResolverNotAllowed
+        );
+
+        // While creation is permissioned the creator must have an allowlist entry
+        require!(
+            global.creation_mode == CreationMode::Permissionless || ctx.accounts.creator_entry.is_some(),
+            ErrorCode::CreatorNotAllowed
+        );
+
+        let metadata = &mut ctx.accounts.market_metadata;
+        metadata.market = market_key;
+        metadata.question = question;
+        metadata.description = description;
+        metadata.rules_uri = rules_uri;
+        metadata.metadata_uri = metadata_uri;
+        metadata.evidence = None;
+        metadata.bump = ctx.bumps.market_metadata;
+
+        // Anti-spam: creation fee to the treasury (global PDA), bond escrowed in the market account.
+        // Transfers run before the market is loaded so its data isn't borrowed during CPIs.
+        if global.creation_fee > 0 {
+            let fee_instruction = system_program::Transfer {
+                from: ctx.accounts.creator.to_account_info(),
+                to: global.to_account_info(),
+            };
+            system_program::transfer(
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), fee_instruction),
+                global.creation_fee,
+            )?;
+        }
+        if global.creator_bond > 0 {
+            let bond_instruction = system_program::Transfer {
+                from: ctx.accounts.creator.to_account_info(),
+                to: ctx.accounts.market.to_account_info(),
+            };
+            system_program::transfer(
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), bond_instruction),
+                global.creator_bond,
+            )?;
+        }
+
+        // Optional creator seed, split across both pools so the first bettor sees real odds
+        let (seed_yes, seed_no) = if seed_amount > 0 {
+            let (
+                Some(position),
+                Some(creator_token_account),
+                Some(market_token_account),
+            ) = (
+                ctx.accounts.creator_position.as_mut(),
+                ctx.accounts.creator_token_account.as_ref(),
+                ctx.accounts.market_token_account.as_ref(),
+            ) else {
+                return err!(ErrorCode::MissingSeedAccounts);
+            };
+
+            require!(
+                creator_token_account.mint == required_token_mint,
+                ErrorCode::WrongToken
+            );
+
+            let transfer_instruction = Transfer {
+                from: creator_token_account.to_account_info(),
+                to: market_token_account.to_account_info(),
+                authority: ctx.accounts.creator.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+            );
+
+            token::transfer(cpi_ctx, seed_amount)?;
+
+            // Seeds count at 1x weight and as regular stakes for payouts, but not as bets
+            let seed_yes = (seed_amount as u128 * seed_yes_bps as u128 / 10000) as u64;
+            let seed_no = seed_amount - seed_yes;
+
+            position.market = market_key;
+            position.user = ctx.accounts.creator.key();
+            position.yes_amount = seed_yes;
+            position.no_amount = seed_no;
+            position.yes_bets = 0;
+            position.no_bets = 0;
+            position.yes_weighted = seed_yes;
+            position.no_weighted = seed_no;
+            position.bump = ctx.bumps.creator_position;
+
+            msg!("Market seeded by creator: {} YES / {} NO", seed_yes, seed_no);
+            (seed_yes, seed_no)
+        } else {
+            // An unseeded market must not open an empty creator position
+            require!(
+                ctx.accounts.creator_position.is_none(),
+                ErrorCode::UnexpectedSeedPosition
+            );
+            (0, 0)
+        };
+
+        // Fields not set here start zeroed: Active, Parimutuel, no result, empty LMSR/AMM state
+        let mut market = ctx.accounts.market.load_init()?;
         market.id = global.market_count;
         market.creator = ctx.accounts.creator.key();
-        market.question = question;
-        market.description = description;
+        market.set_resolver(&resolver);
+        market.rules_hash = rules_hash;
+        market.created_at = Clock::get()?.unix_timestamp;
         market.closing_time = closing_time;
+        market.weight_closing_time = closing_time;
+        market.freeze_window = freeze_window;
+        market.set_time_weight(&time_weight);
+        market.min_bet = min_bet;
+        market.max_bet = max_bet;
+        market.max_position = max_position;
+        market.max_pool_share_bps = max_pool_share_bps;
+        market.set_holder_gate(&holder_gate);
+        market.allowlist_root = allowlist_root;
+        market.claim_window = claim_window;
+        market.set_bet_record_mode(&bet_record_mode);
         market.required_token_mint = required_token_mint;
-        market.required_token_symbol = required_token_symbol;
-        market.required_token_name = required_token_name;
-        market.status = MarketStatus::Active;
-        market.yes_pool = 0;
-        market.no_pool = 0;
-        market.yes_bets = 0;
-        market.no_bets = 0;
-        market.result = None;
+        market.required_token_symbol = fixed_bytes(&required_token_symbol);
+        market.required_token_name = fixed_bytes(&required_token_name);
+        market.metadata_hash = metadata_hash;
+        market.set_status(MarketStatus::Active);
+        market.set_pricing_mode(PricingMode::Parimutuel);
+        market.seed_amount = seed_amount;
+        market.yes_pool = seed_yes;
+        market.no_pool = seed_no;
+        market.yes_weighted_pool = seed_yes;
+        market.no_weighted_pool = seed_no;
+        market.open_positions = if seed_amount > 0 { 1 } else { 0 };
+        market.creator_bond = global.creator_bond;
         market.bump = ctx.bumps.market;
 
         global.market_count += 1;
 
-        msg!("Market created with ID: {} - Required token: {}", market.id, market.required_token_symbol);
+        msg!("Market created with ID: {} - Required token: {}", market.id, required_token_symbol);
         Ok(())
     }
 
@@ -70,62 +485,117 @@ pub mod prediction_market {
         ctx: Context<PlaceBet>,
         amount: u64,
         side: BetSide,
+        min_payout_multiplier_bps: u64,
+        allowlist_proof: Vec<[u8; 32]>,
     ) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+        let mut market = ctx.accounts.market.load_mut()?;
         let position = &mut ctx.accounts.position;
-        let bet = &mut ctx.accounts.bet;
         let global = &mut ctx.accounts.global;
 
         // Check if market is still active
         require!(
-            market.status == MarketStatus::Active,
+            market.status() == MarketStatus::Active,
             ErrorCode::MarketNotActive
         );
 
+        // LMSR and AMM markets trade through their own instructions
+        require!(
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
+        );
+
         // Check if market hasn't closed
+        let now = Clock::get()?.unix_timestamp;
         require!(
-            Clock::get()?.unix_timestamp < market.closing_time,
+            now < market.closing_time,
             ErrorCode::MarketClosed
         );
 
+        // Check if betting hasn't been frozen ahead of closing
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+
         // Verify the user is using the correct token for this market
         require!(
             ctx.accounts.user_token_account.mint == market.required_token_mint,
             ErrorCode::WrongToken
         );
 
+        // Holder-only markets check the gating accounts the user supplied
+        market.holder_gate().verify(
+            &ctx.accounts.user.key(),
+            ctx.accounts.gate_token_account.as_deref(),
+            ctx.accounts.gate_metadata.as_deref(),
+        )?;
+
+        require!(amount >= market.min_bet, ErrorCode::BetBelowMinimum);
+        require!(
+            market.max_bet == 0 || amount <= market.max_bet,
+            ErrorCode::BetAboveMaximum
+        );
+
         // Calculate platform fee
         let fee_amount = (amount * PLATFORM_FEE_BASIS_POINTS as u64) / 10000;
         let bet_amount = amount - fee_amount;
 
+        // Earlier bets carry more weight when the market has a time-weight curve
+        let weight_bps = market.time_weight_bps(now);
+        let weighted_amount = (bet_amount as u128 * weight_bps as u128 / 10000) as u64;
+
         // Initialize or update position
         if position.market == Pubkey::default() {
+            // Private markets check the allowlist once, before the position exists
+            if market.is_private() {
+                require!(
+                    merkle::verify(&market.allowlist_root, &ctx.accounts.user.key(), &allowlist_proof),
+                    ErrorCode::NotOnAllowlist
+                );
+            }
+
             // First bet for this user on this market
-            position.market = market.key();
+            position.market = ctx.accounts.market.key();
             position.user = ctx.accounts.user.key();
             position.yes_amount = 0;
             position.no_amount = 0;
+            position.yes_bets = 0;
+            position.no_bets = 0;
+            position.yes_weighted = 0;
+            position.no_weighted = 0;
             position.bump = ctx.bumps.position;
+            market.open_positions += 1;
         }
 
         // Update position based on side
         match side {
             BetSide::Yes => {
                 position.yes_amount += bet_amount;
+                position.yes_weighted += weighted_amount;
+                position.yes_bets += 1;
             }
             BetSide::No => {
                 position.no_amount += bet_amount;
+                position.no_weighted += weighted_amount;
+                position.no_bets += 1;
             }
         }
 
-        // Initialize bet record
-        bet.market = market.key();
-        bet.user = ctx.accounts.user.key();
-        bet.amount = bet_amount;
-        bet.side = side;
-        bet.timestamp = Clock::get()?.unix_timestamp;
-        bet.claimed = false;
-        bet.bump = ctx.bumps.bet;
+        // Initialize bet record; event-only markets keep history in BetPlaced events instead
+        match (&market.bet_record_mode(), ctx.accounts.bet.as_mut()) {
+            (BetRecordMode::Account, Some(bet)) => {
+                bet.market = ctx.accounts.market.key();
+                bet.user = ctx.accounts.user.key();
+                bet.amount = bet_amount;
+                bet.weighted_amount = weighted_amount;
+                bet.side = side.clone();
+                bet.timestamp = now;
+                bet.claimed = false;
+                bet.bump = ctx.bumps.bet;
+            }
+            (BetRecordMode::EventOnly, None) => {}
+            _ => return err!(ErrorCode::BetRecordModeMismatch),
+        }
 
         // Transfer tokens to market (including fee)
         let transfer_instruction = Transfer {
@@ -159,84 +629,93 @@ pub mod prediction_market {
         match side {
             BetSide::Yes => {
                 market.yes_pool += bet_amount;
+                market.yes_weighted_pool += weighted_amount;
                 market.yes_bets += 1;
             }
             BetSide::No => {
                 market.no_pool += bet_amount;
+                market.no_weighted_pool += weighted_amount;
                 market.no_bets += 1;
             }
         }
 
-        global.total_volume += amount;
-        global.total_fees_collected += fee_amount;
-
-        msg!("Bet placed: {} {} tokens on {:?} (fee: {})", bet_amount, market.required_token_symbol, side, fee_amount);
-        Ok(())
-    }
-
-    // Resolve market (call this after market closes)
-    pub fn resolve_market(
-        ctx: Context<ResolveMarket>,
-        result: BetSide,
-    ) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+        // Exposure caps apply to the user's aggregated position after this bet
+        let (position_side, market_side) = match side {
+            BetSide::Yes => (position.yes_amount, market.yes_pool),
+            BetSide::No => (position.no_amount, market.no_pool),
+        };
+        require!(
+            market.max_position == 0 || position.yes_amount + position.no_amount <= market.max_position,
+            ErrorCode::PositionLimitExceeded
+        );
 
-        // Check if market is closed
+        // A shallow side is measured as if it held the platform's minimum depth, so an
+        // opening bet can't own all of it; virtual liquidity counts towards the depth
+        let side_pool = (market_side as u128 + market.virtual_liquidity as u128)
+            .max(global.min_side_depth as u128);
         require!(
-            Clock::get()?.unix_timestamp >= market.closing_time,
-            ErrorCode::MarketNotClosed
+            market.max_pool_share_bps == 0
+                || position_side as u128 * 10000 <= side_pool * market.max_pool_share_bps as u128,
+            ErrorCode::PoolShareExceeded
         );
 
-        // Check if market is still active
+        // Slippage protection: the post-bet odds must still pay at least what the user signed for
+        let payout_multiplier_bps = market.payout_multiplier_bps(&side) * weight_bps / 10000;
         require!(
-            market.status == MarketStatus::Active,
-            ErrorCode::MarketNotActive
+            payout_multiplier_bps >= min_payout_multiplier_bps,
+            ErrorCode::PayoutBelowMinimum
         );
 
-        market.result = Some(result);
-        market.status = MarketStatus::Resolved;
+        global.total_volume += amount;
+        global.total_fees_collected += fee_amount;
 
-        msg!("Market resolved with result: {:?}", result);
+        market.bet_sequence += 1;
+        emit!(BetPlaced {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            sequence: market.bet_sequence,
+            side: side.clone(),
+            amount,
+            fee: fee_amount,
+            stake: bet_amount,
+            weighted_stake: weighted_amount,
+            yes_pool: market.yes_pool,
+            no_pool: market.no_pool,
+            payout_multiplier_bps,
+            timestamp: now,
+        });
+
+        msg!("Bet placed: {} {} tokens on {:?} (fee: {})", bet_amount, market.symbol(), side, fee_amount);
         Ok(())
     }
 
-    // Claim winnings for a specific bet
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-        let market = &ctx.accounts.market;
-        let position = &mut ctx.accounts.position;
-        let bet = &ctx.accounts.bet;
+    // Switch a fresh market to LMSR pricing, funded by the creator with b * ln(2)
+    pub fn initialize_lmsr(ctx: Context<InitializeLmsr>, liquidity: u64) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
 
-        // Check if market is resolved
         require!(
-            market.status == MarketStatus::Resolved,
-            ErrorCode::MarketNotResolved
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
         );
-
-        // Check if user bet on the winning side
         require!(
-            market.result == Some(bet.side),
-            ErrorCode::NotWinningBet
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
         );
 
-        // Check if bet hasn't been claimed yet
+        // Pricing can only change before anyone has bet or seeded the pools
         require!(
-            !bet.claimed,
-            ErrorCode::AlreadyClaimed
+            market.yes_bets == 0 && market.no_bets == 0 && market.yes_pool == 0 && market.no_pool == 0,
+            ErrorCode::MarketHasBets
         );
+        require!(liquidity > 0, ErrorCode::InvalidLiquidity);
 
-        // Calculate winnings (proportional to bet size within winning pool)
-        let total_pool = market.yes_pool + market.no_pool;
-        let winning_pool = match bet.side {
-            BetSide::Yes => market.yes_pool,
-            BetSide::No => market.no_pool,
-        };
-
-        let winnings = (total_pool * bet.amount) / winning_pool;
+        // Fund the worst-case market maker loss
+        let funding = lmsr::max_loss(liquidity);
 
-        // Transfer winnings to user
         let transfer_instruction = Transfer {
-            from: ctx.accounts.market_token_account.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
+            from: ctx.accounts.creator_token_account.to_account_info(),
+            to: ctx.accounts.market_token_account.to_account_info(),
+            authority: ctx.accounts.creator.to_account_info(),
         };
 
         let cpi_ctx = CpiContext::new(
@@ -244,64 +723,69 @@ pub mod prediction_market {
             transfer_instruction,
         );
 
-        token::transfer(cpi_ctx, winnings)?;
+        token::transfer(cpi_ctx, funding)?;
 
-        // Mark bet as claimed
-        let bet_account = &mut ctx.accounts.bet;
-        bet_account.claimed = true;
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        return_creator_bond(&mut market, &ctx.accounts.market.to_account_info(), &ctx.accounts.creator.to_account_info())?;
 
-        // Update position (reduce the claimed amount)
-        match bet.side {
-            BetSide::Yes => {
-                position.yes_amount = position.yes_amount.saturating_sub(bet.amount);
-            }
-            BetSide::No => {
-                position.no_amount = position.no_amount.saturating_sub(bet.amount);
-            }
-        }
+        market.set_pricing_mode(PricingMode::Lmsr);
+        market.lmsr = LmsrState {
+            liquidity,
+            yes_shares: 0,
+            no_shares: 0,
+            funding,
+            collateral: funding,
+        };
 
-        msg!("Winnings claimed: {} tokens for bet amount: {}", winnings, bet.amount);
+        msg!("Market {} switched to LMSR with b = {} (funding: {})", market.id, liquidity, funding);
         Ok(())
     }
 
-    // Claim all winnings for a user's position in a market
-    pub fn claim_all_winnings(ctx: Context<ClaimAllWinnings>) -> Result<()> {
-        let market = &ctx.accounts.market;
-        let position = &mut ctx.accounts.position;
+    // Buy LMSR shares of one side at the current price
+    pub fn buy_shares(
+        ctx: Context<BuyShares>,
+        side: BetSide,
+        shares: u64,
+        max_cost: u64,
+    ) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let now = Clock::get()?.unix_timestamp;
 
-        // Check if market is resolved
         require!(
-            market.status == MarketStatus::Resolved,
-            ErrorCode::MarketNotResolved
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
         );
+        require!(
+            market.pricing_mode() == PricingMode::Lmsr,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+        require!(shares > 0, ErrorCode::InvalidShareAmount);
 
-        let total_pool = market.yes_pool + market.no_pool;
-        let mut total_winnings = 0u64;
-
-        // Calculate winnings for winning side
-        if let Some(result) = market.result {
-            match result {
-                BetSide::Yes => {
-                    if position.yes_amount > 0 {
-                        total_winnings = (total_pool * position.yes_amount) / market.yes_pool;
-                        position.yes_amount = 0;
-                    }
-                }
-                BetSide::No => {
-                    if position.no_amount > 0 {
-                        total_winnings = (total_pool * position.no_amount) / market.no_pool;
-                        position.no_amount = 0;
-                    }
-                }
-            }
-        }
+        let (q_side, q_other) = market.lmsr.quantities(&side);
+        let cost = lmsr::buy_cost(q_side, q_other, market.lmsr.liquidity, shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let fee_amount = (cost as u128 * PLATFORM_FEE_BASIS_POINTS as u128 / 10000) as u64;
+        let total_cost = cost.checked_add(fee_amount).ok_or(ErrorCode::MathOverflow)?;
 
-        require!(total_winnings > 0, ErrorCode::NoWinningsToClaim);
+        require!(
+            total_cost <= max_cost,
+            ErrorCode::SlippageExceeded
+        );
 
-        // Transfer total winnings to user
+        // Transfer cost to the market vault
         let transfer_instruction = Transfer {
-            from: ctx.accounts.market_token_account.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.market_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
         };
 
         let cpi_ctx = CpiContext::new(
@@ -309,182 +793,3198 @@ pub mod prediction_market {
             transfer_instruction,
         );
 
-        token::transfer(cpi_ctx, total_winnings)?;
-
-        msg!("All winnings claimed: {} tokens", total_winnings);
-        Ok(())
-    }
-}
+        token::transfer(cpi_ctx, cost)?;
+
+        // Transfer fee to platform
+        if fee_amount > 0 {
+            let fee_transfer_instruction = Transfer {
+                from: ctx.accounts.user_token_account.to_account_info(),
+                to: ctx.accounts.platform_token_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+
+            let fee_cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                fee_transfer_instruction,
+            );
+
+            token::transfer(fee_cpi_ctx, fee_amount)?;
+        }
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let position = &mut ctx.accounts.position;
+        let global = &mut ctx.accounts.global;
+
+        if position.market == Pubkey::default() {
+            position.market = ctx.accounts.market.key();
+            position.user = ctx.accounts.user.key();
+            position.yes_amount = 0;
+            position.no_amount = 0;
+            position.yes_bets = 0;
+            position.no_bets = 0;
+            position.yes_weighted = 0;
+            position.no_weighted = 0;
+            position.bump = ctx.bumps.position;
+            market.open_positions += 1;
+        }
+
+        // Positions on LMSR markets hold shares
+        match side {
+            BetSide::Yes => {
+                position.yes_amount += shares;
+                position.yes_bets += 1;
+                market.lmsr.yes_shares += shares;
+                market.lmsr.collateral += cost;
+                market.yes_bets += 1;
+            }
+            BetSide::No => {
+                position.no_amount += shares;
+                position.no_bets += 1;
+                market.lmsr.no_shares += shares;
+                market.lmsr.collateral += cost;
+                market.no_bets += 1;
+            }
+        }
+
+        global.total_volume += total_cost;
+        global.total_fees_collected += fee_amount;
+
+        emit!(SharesTraded {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            side: side.clone(),
+            shares,
+            collateral: cost,
+            is_buy: true,
+            price_yes_bps: market.lmsr.price_yes_bps(),
+        });
+
+        msg!("Bought {} {:?} shares for {} (fee: {})", shares, side, cost, fee_amount);
+        Ok(())
+    }
+
+    // Sell LMSR shares of one side back to the market maker
+    pub fn sell_shares(
+        ctx: Context<SellShares>,
+        side: BetSide,
+        shares: u64,
+        min_proceeds: u64,
+    ) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let position = &ctx.accounts.position;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::Lmsr,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+        require!(shares > 0, ErrorCode::InvalidShareAmount);
+
+        let position_shares = match side {
+            BetSide::Yes => position.yes_amount,
+            BetSide::No => position.no_amount,
+        };
+        require!(shares <= position_shares, ErrorCode::InsufficientPosition);
+
+        let (q_side, q_other) = market.lmsr.quantities(&side);
+        let proceeds = lmsr::sell_proceeds(q_side, q_other, market.lmsr.liquidity, shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        require!(
+            proceeds >= min_proceeds,
+            ErrorCode::SlippageExceeded
+        );
+
+        // Transfer proceeds from the market vault to the user
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, proceeds)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let position = &mut ctx.accounts.position;
+
+        match side {
+            BetSide::Yes => {
+                position.yes_amount -= shares;
+                market.lmsr.yes_shares -= shares;
+            }
+            BetSide::No => {
+                position.no_amount -= shares;
+                market.lmsr.no_shares -= shares;
+            }
+        }
+        market.lmsr.collateral -= proceeds;
+
+        emit!(SharesTraded {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            side: side.clone(),
+            shares,
+            collateral: proceeds,
+            is_buy: false,
+            price_yes_bps: market.lmsr.price_yes_bps(),
+        });
+
+        msg!("Sold {} {:?} shares for {}", shares, side, proceeds);
+        Ok(())
+    }
+
+    // Return the creator's unused LMSR funding once the market is resolved
+    pub fn withdraw_lmsr_liquidity(ctx: Context<WithdrawLmsrLiquidity>) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(
+            market.pricing_mode() == PricingMode::Lmsr,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            market.status() == MarketStatus::Resolved,
+            ErrorCode::MarketNotResolved
+        );
+
+        // Only the market maker's own collateral is paid out, less what every
+        // unclaimed winning share still redeems; outcome-token collateral and
+        // order escrow sharing the vault are never touched
+        let surplus = market.lmsr.surplus(market.result().as_ref());
+        require!(surplus > 0, ErrorCode::NothingToWithdraw);
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, surplus)?;
+
+        drop(market);
+        ctx.accounts.market.load_mut()?.lmsr.collateral -= surplus;
+
+        msg!("LMSR liquidity withdrawn: {} tokens", surplus);
+        Ok(())
+    }
+
+    // Switch a fresh market to a constant-product outcome-token AMM
+    pub fn initialize_amm(ctx: Context<InitializeAmm>, lp_fee_basis_points: u16) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
+        );
+
+        // Pricing can only change before anyone has bet or seeded the pools
+        require!(
+            market.yes_bets == 0 && market.no_bets == 0 && market.yes_pool == 0 && market.no_pool == 0,
+            ErrorCode::MarketHasBets
+        );
+        require!(
+            lp_fee_basis_points <= MAX_LP_FEE_BASIS_POINTS,
+            ErrorCode::InvalidFee
+        );
+
+        return_creator_bond(&mut market, &ctx.accounts.market.to_account_info(), &ctx.accounts.creator.to_account_info())?;
+
+        market.set_pricing_mode(PricingMode::ConstantProduct);
+        market.amm = AmmState {
+            yes_reserve: 0,
+            no_reserve: 0,
+            lp_supply: 0,
+            lp_fee_basis_points,
+            _padding: [0; 6],
+        };
+
+        msg!("Market {} switched to constant-product AMM (LP fee: {} bps)", market.id, lp_fee_basis_points);
+        Ok(())
+    }
+
+    // Deposit collateral as liquidity; shares that don't fit the pool ratio go to the provider's position
+    pub fn add_liquidity(ctx: Context<AddLiquidity>, amount: u64) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::ConstantProduct,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            Clock::get()?.unix_timestamp < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+        require!(amount > 0, ErrorCode::InvalidLiquidity);
+
+        let (yes_added, no_added, lp_minted) = cpmm::deposit(
+            market.amm.yes_reserve,
+            market.amm.no_reserve,
+            market.amm.lp_supply,
+            amount,
+        )
+        .ok_or(ErrorCode::MathOverflow)?;
+        require!(lp_minted > 0, ErrorCode::InvalidLiquidity);
+
+        // Each deposited token mints one complete YES/NO set
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.market_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+        );
+
+        token::transfer(cpi_ctx, amount)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let liquidity_position = &mut ctx.accounts.liquidity_position;
+        let position = &mut ctx.accounts.position;
+
+        if liquidity_position.market == Pubkey::default() {
+            liquidity_position.market = ctx.accounts.market.key();
+            liquidity_position.provider = ctx.accounts.user.key();
+            liquidity_position.lp_shares = 0;
+            liquidity_position.bump = ctx.bumps.liquidity_position;
+        }
+        if position.market == Pubkey::default() {
+            position.market = ctx.accounts.market.key();
+            position.user = ctx.accounts.user.key();
+            position.yes_amount = 0;
+            position.no_amount = 0;
+            position.yes_bets = 0;
+            position.no_bets = 0;
+            position.yes_weighted = 0;
+            position.no_weighted = 0;
+            position.bump = ctx.bumps.position;
+            market.open_positions += 1;
+        }
+
+        market.amm.yes_reserve += yes_added;
+        market.amm.no_reserve += no_added;
+        market.amm.lp_supply += lp_minted;
+        liquidity_position.lp_shares += lp_minted;
+        position.yes_amount += amount - yes_added;
+        position.no_amount += amount - no_added;
+
+        emit!(LiquidityChanged {
+            market: ctx.accounts.market.key(),
+            provider: ctx.accounts.user.key(),
+            collateral: amount,
+            lp_shares: lp_minted,
+            is_deposit: true,
+        });
+
+        msg!("Liquidity added: {} tokens for {} LP shares", amount, lp_minted);
+        Ok(())
+    }
+
+    // Burn LP shares; matched YES/NO sets are paid out as collateral, the rest go to the position.
+    // After resolution the LP's share of the winning reserve is paid out instead.
+    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, lp_shares: u64) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(
+            market.pricing_mode() == PricingMode::ConstantProduct,
+            ErrorCode::WrongPricingMode
+        );
+        require!(lp_shares > 0, ErrorCode::InvalidShareAmount);
+        require!(
+            lp_shares <= ctx.accounts.liquidity_position.lp_shares,
+            ErrorCode::InsufficientPosition
+        );
+
+        let (yes_out, no_out) = cpmm::withdraw(
+            market.amm.yes_reserve,
+            market.amm.no_reserve,
+            market.amm.lp_supply,
+            lp_shares,
+        )
+        .ok_or(ErrorCode::MathOverflow)?;
+
+        // While trading, a complete set is always worth one token, so merge them straight
+        // back and credit the rest to the position. Once resolved, only the winning
+        // reserve has value and it is paid out 1:1, exactly as claiming would.
+        let (collateral, yes_credit, no_credit) = match (market.status(), market.result()) {
+            (MarketStatus::Active, _) => {
+                let sets = yes_out.min(no_out);
+                (sets, yes_out - sets, no_out - sets)
+            }
+            (MarketStatus::Resolved, Some(BetSide::Yes)) => (yes_out, 0, 0),
+            (MarketStatus::Resolved, Some(BetSide::No)) => (no_out, 0, 0),
+            _ => return err!(ErrorCode::MarketNotActive),
+        };
+
+        if collateral > 0 {
+            let market_id = market.id.to_le_bytes();
+            let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+            let signer = &[&seeds[..]];
+
+            let transfer_instruction = Transfer {
+                from: ctx.accounts.market_token_account.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: ctx.accounts.market.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            );
+
+            token::transfer(cpi_ctx, collateral)?;
+        }
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let liquidity_position = &mut ctx.accounts.liquidity_position;
+        let position = &mut ctx.accounts.position;
+
+        if position.market == Pubkey::default() {
+            position.market = ctx.accounts.market.key();
+            position.user = ctx.accounts.user.key();
+            position.yes_amount = 0;
+            position.no_amount = 0;
+            position.yes_bets = 0;
+            position.no_bets = 0;
+            position.yes_weighted = 0;
+            position.no_weighted = 0;
+            position.bump = ctx.bumps.position;
+            market.open_positions += 1;
+        }
+
+        market.amm.yes_reserve -= yes_out;
+        market.amm.no_reserve -= no_out;
+        market.amm.lp_supply -= lp_shares;
+        liquidity_position.lp_shares -= lp_shares;
+        position.yes_amount += yes_credit;
+        position.no_amount += no_credit;
+
+        emit!(LiquidityChanged {
+            market: ctx.accounts.market.key(),
+            provider: ctx.accounts.user.key(),
+            collateral,
+            lp_shares,
+            is_deposit: false,
+        });
+
+        msg!("Liquidity removed: {} LP shares for {} tokens (+{} YES / {} NO shares)", lp_shares, collateral, yes_credit, no_credit);
+        Ok(())
+    }
+
+    // Swap collateral for outcome shares of one side
+    pub fn swap_collateral_for_shares(
+        ctx: Context<SwapCollateralForShares>,
+        side: BetSide,
+        collateral_in: u64,
+        min_shares_out: u64,
+    ) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::ConstantProduct,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+        require!(
+            market.amm.yes_reserve > 0 && market.amm.no_reserve > 0,
+            ErrorCode::NoLiquidity
+        );
+        require!(collateral_in > 0, ErrorCode::InvalidShareAmount);
+
+        let fee = (collateral_in as u128 * market.amm.lp_fee_basis_points as u128 / 10000) as u64;
+        let (reserve_side, reserve_other) = market.amm.reserves(&side);
+        let shares = cpmm::shares_out(reserve_side, reserve_other, collateral_in - fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(shares > 0, ErrorCode::InvalidShareAmount);
+
+        require!(
+            shares >= min_shares_out,
+            ErrorCode::SlippageExceeded
+        );
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.market_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+        );
+
+        token::transfer(cpi_ctx, collateral_in)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let position = &mut ctx.accounts.position;
+        let global = &mut ctx.accounts.global;
+
+        if position.market == Pubkey::default() {
+            position.market = ctx.accounts.market.key();
+            position.user = ctx.accounts.user.key();
+            position.yes_amount = 0;
+            position.no_amount = 0;
+            position.yes_bets = 0;
+            position.no_bets = 0;
+            position.yes_weighted = 0;
+            position.no_weighted = 0;
+            position.bump = ctx.bumps.position;
+            market.open_positions += 1;
+        }
+
+        // The traded-in amount mints complete sets, the fee's sets stay in the pool for LPs
+        let minted = collateral_in - fee;
+        match side {
+            BetSide::Yes => {
+                market.amm.yes_reserve = market.amm.yes_reserve + minted - shares + fee;
+                market.amm.no_reserve += minted + fee;
+                position.yes_amount += shares;
+                position.yes_bets += 1;
+                market.yes_bets += 1;
+            }
+            BetSide::No => {
+                market.amm.no_reserve = market.amm.no_reserve + minted - shares + fee;
+                market.amm.yes_reserve += minted + fee;
+                position.no_amount += shares;
+                position.no_bets += 1;
+                market.no_bets += 1;
+            }
+        }
+
+        global.total_volume += collateral_in;
+
+        emit!(SharesTraded {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            side: side.clone(),
+            shares,
+            collateral: collateral_in,
+            is_buy: true,
+            price_yes_bps: market.amm.price_yes_bps(),
+        });
+
+        msg!("Swapped {} tokens for {} {:?} shares (LP fee: {})", collateral_in, shares, side, fee);
+        Ok(())
+    }
+
+    // Swap outcome shares of one side back to collateral
+    pub fn swap_shares_for_collateral(
+        ctx: Context<SwapSharesForCollateral>,
+        side: BetSide,
+        shares_in: u64,
+        min_collateral_out: u64,
+    ) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let position = &ctx.accounts.position;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::ConstantProduct,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+        require!(shares_in > 0, ErrorCode::InvalidShareAmount);
+
+        let position_shares = match side {
+            BetSide::Yes => position.yes_amount,
+            BetSide::No => position.no_amount,
+        };
+        require!(shares_in <= position_shares, ErrorCode::InsufficientPosition);
+
+        let (reserve_side, reserve_other) = market.amm.reserves(&side);
+        let burned = cpmm::collateral_out(reserve_side, reserve_other, shares_in)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let fee = (burned as u128 * market.amm.lp_fee_basis_points as u128 / 10000) as u64;
+        let collateral = burned - fee;
+        require!(collateral > 0, ErrorCode::InvalidShareAmount);
+
+        require!(
+            collateral >= min_collateral_out,
+            ErrorCode::SlippageExceeded
+        );
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, collateral)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let position = &mut ctx.accounts.position;
+        let global = &mut ctx.accounts.global;
+
+        // `burned` complete sets leave the pool; the fee's sets are minted back for LPs
+        match side {
+            BetSide::Yes => {
+                market.amm.yes_reserve = market.amm.yes_reserve + shares_in - burned + fee;
+                market.amm.no_reserve = market.amm.no_reserve - burned + fee;
+                position.yes_amount -= shares_in;
+            }
+            BetSide::No => {
+                market.amm.no_reserve = market.amm.no_reserve + shares_in - burned + fee;
+                market.amm.yes_reserve = market.amm.yes_reserve - burned + fee;
+                position.no_amount -= shares_in;
+            }
+        }
+
+        global.total_volume += burned;
+
+        emit!(SharesTraded {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            side: side.clone(),
+            shares: shares_in,
+            collateral,
+            is_buy: false,
+            price_yes_bps: market.amm.price_yes_bps(),
+        });
+
+        msg!("Swapped {} {:?} shares for {} tokens (LP fee: {})", shares_in, side, collateral, fee);
+        Ok(())
+    }
+
+    // Create the market's YES/NO outcome token mints
+    pub fn create_outcome_mints(ctx: Context<CreateOutcomeMints>) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+
+        require!(
+            market.yes_mint == Pubkey::default(),
+            ErrorCode::OutcomeMintsAlreadyCreated
+        );
+
+        market.yes_mint = ctx.accounts.yes_mint.key();
+        market.no_mint = ctx.accounts.no_mint.key();
+
+        msg!("Outcome mints created for market {}: YES {} / NO {}", market.id, market.yes_mint, market.no_mint);
+        Ok(())
+    }
+
+    // Deposit collateral to mint one YES and one NO token per token deposited
+    pub fn mint_outcome_tokens(ctx: Context<MintOutcomeTokens>, amount: u64) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            Clock::get()?.unix_timestamp < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+        require!(amount > 0, ErrorCode::InvalidShareAmount);
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.market_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+        );
+
+        token::transfer(cpi_ctx, amount)?;
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        for (mint, to) in [
+            (&ctx.accounts.yes_mint, &ctx.accounts.user_yes_account),
+            (&ctx.accounts.no_mint, &ctx.accounts.user_no_account),
+        ] {
+            let mint_instruction = MintTo {
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: ctx.accounts.market.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                mint_instruction,
+                signer,
+            );
+
+            token::mint_to(cpi_ctx, amount)?;
+        }
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        market.outcome_collateral += amount;
+
+        msg!("Minted {} YES/NO outcome token pairs", amount);
+        Ok(())
+    }
+
+    // Burn matching YES and NO tokens to get the collateral back
+    pub fn burn_outcome_tokens(ctx: Context<BurnOutcomeTokens>, amount: u64) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(amount > 0, ErrorCode::InvalidShareAmount);
+
+        for (mint, from) in [
+            (&ctx.accounts.yes_mint, &ctx.accounts.user_yes_account),
+            (&ctx.accounts.no_mint, &ctx.accounts.user_no_account),
+        ] {
+            let burn_instruction = Burn {
+                mint: mint.to_account_info(),
+                from: from.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                burn_instruction,
+            );
+
+            token::burn(cpi_ctx, amount)?;
+        }
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, amount)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        market.outcome_collateral -= amount;
+
+        msg!("Burned {} YES/NO outcome token pairs", amount);
+        Ok(())
+    }
+
+    // Redeem winning outcome tokens 1:1 for collateral after resolution. On a cancelled
+    // market each complete set is refunded, so either token redeems for half a token.
+    pub fn redeem_outcome_tokens(ctx: Context<RedeemOutcomeTokens>, amount: u64) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(amount > 0, ErrorCode::InvalidShareAmount);
+
+        let mint = ctx.accounts.winning_mint.key();
+        let payout = match (market.status(), market.result()) {
+            // Only the winning side's mint can be redeemed
+            (MarketStatus::Resolved, Some(BetSide::Yes)) if mint == market.yes_mint => amount,
+            (MarketStatus::Resolved, Some(BetSide::No)) if mint == market.no_mint => amount,
+            (MarketStatus::Resolved, _) => return err!(ErrorCode::NotWinningBet),
+            // Rounds down, so redeem even amounts to avoid losing half a base unit
+            (MarketStatus::Cancelled, _) if mint == market.yes_mint || mint == market.no_mint => amount / 2,
+            (MarketStatus::Cancelled, _) => return err!(ErrorCode::OutcomeMintMismatch),
+            (MarketStatus::Active, _) => return err!(ErrorCode::MarketNotResolved),
+        };
+
+        let burn_instruction = Burn {
+            mint: ctx.accounts.winning_mint.to_account_info(),
+            from: ctx.accounts.user_outcome_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            burn_instruction,
+        );
+
+        token::burn(cpi_ctx, amount)?;
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, payout)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        market.outcome_collateral -= payout;
+
+        msg!("Redeemed {} outcome tokens for {}", amount, payout);
+        Ok(())
+    }
+
+    // Create the YES order book, event queue and escrow vaults for a market
+    pub fn create_order_book(ctx: Context<CreateOrderBook>) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(
+            market.yes_mint != Pubkey::default(),
+            ErrorCode::OutcomeMintsNotCreated
+        );
+
+        let mut order_book = ctx.accounts.order_book.load_init()?;
+        order_book.market = ctx.accounts.market.key();
+        order_book.next_order_id = 0;
+        order_book.bump = ctx.bumps.order_book;
+
+        let mut event_queue = ctx.accounts.event_queue.load_init()?;
+        event_queue.market = ctx.accounts.market.key();
+
+        msg!("Order book created for market {}", market.id);
+        Ok(())
+    }
+
+    // Place a limit order for YES tokens, matching against the book first
+    pub fn place_order(
+        ctx: Context<PlaceOrder>,
+        side: OrderSide,
+        price_bps: u16,
+        quantity: u64,
+        client_order_id: u64,
+    ) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+        require!(
+            price_bps > 0 && price_bps < 10000,
+            ErrorCode::InvalidPrice
+        );
+        require!(
+            quantity >= MIN_ORDER_QUANTITY && quantity % ORDER_LOT_SIZE == 0,
+            ErrorCode::InvalidOrderQuantity
+        );
+
+        let open_orders = &mut ctx.accounts.open_orders;
+        if open_orders.market == Pubkey::default() {
+            open_orders.market = ctx.accounts.market.key();
+            open_orders.owner = ctx.accounts.user.key();
+            open_orders.base_free = 0;
+            open_orders.base_locked = 0;
+            open_orders.quote_free = 0;
+            open_orders.quote_locked = 0;
+            open_orders.bump = ctx.bumps.open_orders;
+        }
+
+        // Lock what the order needs, depositing only what isn't already free
+        let (from, to, deposit) = match side {
+            OrderSide::Bid => {
+                let cost = order_book::quote_amount(quantity, price_bps);
+                let from_free = open_orders.quote_free.min(cost);
+                open_orders.quote_free -= from_free;
+                open_orders.quote_locked += cost;
+                (&ctx.accounts.user_token_account, &ctx.accounts.quote_vault, cost - from_free)
+            }
+            OrderSide::Ask => {
+                let from_free = open_orders.base_free.min(quantity);
+                open_orders.base_free -= from_free;
+                open_orders.base_locked += quantity;
+                (&ctx.accounts.user_yes_account, &ctx.accounts.base_vault, quantity - from_free)
+            }
+        };
+
+        if deposit > 0 {
+            let transfer_instruction = Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+            );
+
+            token::transfer(cpi_ctx, deposit)?;
+        }
+
+        let mut order_book = ctx.accounts.order_book.load_mut()?;
+        let mut event_queue = ctx.accounts.event_queue.load_mut()?;
+
+        let opposite = match side {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        };
+        let crosses = |resting: &Order| match side {
+            OrderSide::Bid => resting.price_bps <= price_bps,
+            OrderSide::Ask => resting.price_bps >= price_bps,
+        };
+
+        // Match against resting orders at the maker's price
+        let mut remaining = quantity;
+        let mut matches = 0;
+        while remaining > 0 && matches < MAX_MATCHES_PER_ORDER {
+            let maker = match order_book.best(opposite) {
+                Some(order) if crosses(order) => *order,
+                _ => break,
+            };
+            let fill = remaining.min(maker.quantity);
+
+            match side {
+                OrderSide::Bid => {
+                    // Locked at our price, paid at the maker's price
+                    let locked = order_book::quote_amount(fill, price_bps);
+                    let paid = order_book::quote_amount(fill, maker.price_bps);
+                    open_orders.quote_locked -= locked;
+                    open_orders.quote_free += locked - paid;
+                    open_orders.base_free += fill;
+                }
+                OrderSide::Ask => {
+                    open_orders.base_locked -= fill;
+                    open_orders.quote_free += order_book::quote_amount(fill, maker.price_bps);
+                }
+            }
+
+            event_queue.push(FillEvent {
+                maker: maker.owner,
+                taker: ctx.accounts.user.key(),
+                order_id: maker.order_id,
+                quantity: fill,
+                price_bps: maker.price_bps,
+                maker_side: match opposite {
+                    OrderSide::Bid => 0,
+                    OrderSide::Ask => 1,
+                },
+                kind: 0,
+                _padding: [0; 4],
+            })?;
+
+            order_book.fill_best(opposite, fill);
+            remaining -= fill;
+            matches += 1;
+        }
+
+        let order_id = order_book.next_order_id;
+        order_book.next_order_id += 1;
+
+        if remaining > 0 {
+            let still_crosses = order_book.best(opposite).map_or(false, crosses);
+            if still_crosses {
+                // Hit the match limit; unlock the rest rather than leave a crossed book
+                match side {
+                    OrderSide::Bid => {
+                        let unlocked = order_book::quote_amount(remaining, price_bps);
+                        open_orders.quote_locked -= unlocked;
+                        open_orders.quote_free += unlocked;
+                    }
+                    OrderSide::Ask => {
+                        open_orders.base_locked -= remaining;
+                        open_orders.base_free += remaining;
+                    }
+                }
+            } else {
+                let evicted = order_book.insert(side, Order {
+                    owner: ctx.accounts.user.key(),
+                    order_id,
+                    client_order_id,
+                    quantity: remaining,
+                    price_bps,
+                    _padding: [0; 6],
+                })?;
+
+                // A full side dropped its worst order; the crank unlocks the evicted maker's funds
+                if let Some(evicted) = evicted {
+                    event_queue.push(FillEvent {
+                        maker: evicted.owner,
+                        taker: Pubkey::default(),
+                        order_id: evicted.order_id,
+                        quantity: evicted.quantity,
+                        price_bps: evicted.price_bps,
+                        maker_side: match side {
+                            OrderSide::Bid => 0,
+                            OrderSide::Ask => 1,
+                        },
+                        kind: 1,
+                        _padding: [0; 4],
+                    })?;
+                }
+            }
+        }
+
+        emit!(OrderPlaced {
+            market: ctx.accounts.market.key(),
+            owner: ctx.accounts.user.key(),
+            order_id,
+            client_order_id,
+            side,
+            price_bps,
+            quantity,
+            filled: quantity - remaining,
+        });
+
+        msg!("Order {} placed: {:?} {} @ {} bps (filled: {})", order_id, side, quantity, price_bps, quantity - remaining);
+        Ok(())
+    }
+
+    // Cancel a resting order and unlock its funds
+    pub fn cancel_order(ctx: Context<CancelOrder>, side: OrderSide, order_id: u64) -> Result<()> {
+        let mut order_book = ctx.accounts.order_book.load_mut()?;
+        let open_orders = &mut ctx.accounts.open_orders;
+
+        let order = order_book
+            .remove(side, order_id)
+            .ok_or(ErrorCode::OrderNotFound)?;
+        require!(
+            order.owner == ctx.accounts.user.key(),
+            ErrorCode::Unauthorized
+        );
+
+        match side {
+            OrderSide::Bid => {
+                let unlocked = order_book::quote_amount(order.quantity, order.price_bps);
+                open_orders.quote_locked -= unlocked;
+                open_orders.quote_free += unlocked;
+            }
+            OrderSide::Ask => {
+                open_orders.base_locked -= order.quantity;
+                open_orders.base_free += order.quantity;
+            }
+        }
+
+        msg!("Order {} cancelled ({} remaining)", order_id, order.quantity);
+        Ok(())
+    }
+
+    // Permissionless crank: credit makers for queued fills and unlock evicted orders
+    //
+    // Pass the makers' OpenOrders accounts as remaining accounts, each once. The
+    // transaction fails with MakerAccountMissing if the next event's maker wasn't supplied.
+    pub fn consume_events<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ConsumeEvents<'info>>,
+        limit: u16,
+    ) -> Result<()> {
+        let market_key = ctx.accounts.market.key();
+        let mut event_queue = ctx.accounts.event_queue.load_mut()?;
+
+        // Each maker must appear once: a stale duplicate written back last would erase fills
+        let mut keys: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|account| account.key()).collect();
+        keys.sort();
+        require!(
+            keys.windows(2).all(|pair| pair[0] != pair[1]),
+            ErrorCode::DuplicateMakerAccount
+        );
+
+        let mut makers = ctx
+            .remaining_accounts
+            .iter()
+            .map(Account::<OpenOrders>::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut consumed = 0u16;
+        while consumed < limit {
+            let event = match event_queue.peek() {
+                Some(event) => *event,
+                None => break,
+            };
+            let maker = match makers
+                .iter_mut()
+                .find(|open_orders| open_orders.owner == event.maker && open_orders.market == market_key)
+            {
+                Some(maker) => maker,
+                None => return err!(ErrorCode::MakerAccountMissing),
+            };
+
+            // A fill pays the maker in the other asset; an out event returns what was locked
+            let quote = order_book::quote_amount(event.quantity, event.price_bps);
+            match event.maker_side() {
+                OrderSide::Bid => {
+                    maker.quote_locked -= quote;
+                    if event.is_out() {
+                        maker.quote_free += quote;
+                    } else {
+                        maker.base_free += event.quantity;
+                    }
+                }
+                OrderSide::Ask => {
+                    maker.base_locked -= event.quantity;
+                    if event.is_out() {
+                        maker.base_free += event.quantity;
+                    } else {
+                        maker.quote_free += quote;
+                    }
+                }
+            }
+
+            event_queue.pop();
+            consumed += 1;
+        }
+
+        for maker in makers.iter() {
+            maker.exit(&crate::ID)?;
+        }
+
+        msg!("Consumed {} fill events ({} left)", consumed, event_queue.count);
+        Ok(())
+    }
+
+    // Withdraw free YES tokens and collateral from the order book escrow
+    pub fn settle_funds(ctx: Context<SettleFunds>) -> Result<()> {
+        let market_key = ctx.accounts.market.key();
+        let bump = ctx.accounts.order_book.load()?.bump;
+        let seeds = &[ORDER_BOOK_SEED.as_bytes(), market_key.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
+        let open_orders = &mut ctx.accounts.open_orders;
+        let base_free = open_orders.base_free;
+        let quote_free = open_orders.quote_free;
+        open_orders.base_free = 0;
+        open_orders.quote_free = 0;
+
+        for (from, to, amount) in [
+            (&ctx.accounts.base_vault, &ctx.accounts.user_yes_account, base_free),
+            (&ctx.accounts.quote_vault, &ctx.accounts.user_token_account, quote_free),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+
+            let transfer_instruction = Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: ctx.accounts.order_book.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            );
+
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        msg!("Settled {} YES tokens and {} collateral", base_free, quote_free);
+        Ok(())
+    }
+
+    // Add protocol-subsidized virtual liquidity to both pools of a fresh parimutuel market
+    pub fn set_virtual_liquidity(ctx: Context<SetVirtualLiquidity>, virtual_liquidity: u64) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let global = &mut ctx.accounts.global;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            market.yes_bets == 0 && market.no_bets == 0,
+            ErrorCode::MarketHasBets
+        );
+        require!(
+            virtual_liquidity > 0 && market.virtual_liquidity == 0,
+            ErrorCode::InvalidLiquidity
+        );
+
+        // The shortfall is always below twice the virtual liquidity, so reserve that much
+        let max_subsidy = virtual_liquidity.checked_mul(2).ok_or(ErrorCode::MathOverflow)?;
+        let committed = global
+            .subsidy_committed
+            .checked_add(max_subsidy)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(committed <= global.subsidy_cap, ErrorCode::SubsidyCapExceeded);
+
+        global.subsidy_committed = committed;
+        market.virtual_liquidity = virtual_liquidity;
+
+        msg!("Market {} given {} virtual liquidity per side", market.id, virtual_liquidity);
+        Ok(())
+    }
+
+    // Move the virtual-liquidity shortfall from the treasury into the market vault
+    // after resolution, releasing the rest of the reserved subsidy
+    pub fn settle_subsidy(ctx: Context<SettleSubsidy>) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let global = &mut ctx.accounts.global;
+
+        require!(
+            market.status() == MarketStatus::Resolved,
+            ErrorCode::MarketNotResolved
+        );
+        require!(
+            market.virtual_liquidity > 0 && market.subsidy_settled == 0,
+            ErrorCode::NothingToWithdraw
+        );
+
+        let shortfall = market.subsidy_shortfall();
+        if shortfall > 0 {
+            let seeds = &[GLOBAL_SEED.as_bytes(), &[global.bump]];
+            let signer = &[&seeds[..]];
+
+            let transfer_instruction = Transfer {
+                from: ctx.accounts.treasury_token_account.to_account_info(),
+                to: ctx.accounts.market_token_account.to_account_info(),
+                authority: global.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            );
+
+            token::transfer(cpi_ctx, shortfall)?;
+        }
+
+        global.subsidy_committed = global
+            .subsidy_committed
+            .saturating_sub(market.virtual_liquidity * 2);
+        market.subsidy_settled = 1;
+
+        msg!("Subsidy settled for market {}: {} tokens from treasury", market.id, shortfall);
+        Ok(())
+    }
+
+    // Withdraw part of a position before close, paying a penalty to the remaining pool
+    pub fn exit_position(
+        ctx: Context<ExitPosition>,
+        amount: u64,
+        side: BetSide,
+    ) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let position = &ctx.accounts.position;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
+        );
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+
+        // Exits freeze together with betting so known outcomes can't be dumped
+        require!(
+            now < market.closing_time.saturating_sub(market.freeze_window),
+            ErrorCode::BettingFrozen
+        );
+
+        let position_amount = match side {
+            BetSide::Yes => position.yes_amount,
+            BetSide::No => position.no_amount,
+        };
+        require!(amount > 0, ErrorCode::InvalidExitAmount);
+        require!(amount <= position_amount, ErrorCode::InsufficientPosition);
+
+        let penalty = (amount as u128 * EXIT_PENALTY_BASIS_POINTS as u128 / 10000) as u64;
+        let refund = amount - penalty;
+
+        // Transfer refund from the market vault back to the user
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, refund)?;
+
+        drop(market);
+        let mut market = ctx.accounts.market.load_mut()?;
+        let position = &mut ctx.accounts.position;
+
+        // Update position and pools; a side exited in full no longer counts its bets
+        // Weighted stake leaves in proportion to the amount withdrawn
+        let weighted = match side {
+            BetSide::Yes => (position.yes_weighted as u128 * amount as u128 / position.yes_amount as u128) as u64,
+            BetSide::No => (position.no_weighted as u128 * amount as u128 / position.no_amount as u128) as u64,
+        };
+
+        match side {
+            BetSide::Yes => {
+                position.yes_amount -= amount;
+                position.yes_weighted -= weighted;
+                market.yes_pool -= amount;
+                market.yes_weighted_pool -= weighted;
+                if position.yes_amount == 0 {
+                    market.yes_bets = market.yes_bets.saturating_sub(position.yes_bets);
+                    position.yes_bets = 0;
+                }
+            }
+            BetSide::No => {
+                position.no_amount -= amount;
+                position.no_weighted -= weighted;
+                market.no_pool -= amount;
+                market.no_weighted_pool -= weighted;
+                if position.no_amount == 0 {
+                    market.no_bets = market.no_bets.saturating_sub(position.no_bets);
+                    position.no_bets = 0;
+                }
+            }
+        }
+
+        // The penalty stays in the vault and is paid out to the winning side
+        market.exit_penalty_pool += penalty;
+
+        emit!(PositionExited {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            side: side.clone(),
+            amount,
+            penalty,
+            refund,
+        });
+
+        msg!("Position exited: {} on {:?} (refund: {}, penalty: {})", amount, side, refund, penalty);
+        Ok(())
+    }
+
+    // Push the closing time later (creator or global authority only)
+    pub fn extend_market(ctx: Context<ExtendMarket>, new_closing_time: i64) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+
+        // Once closing_time has passed the market is awaiting resolution
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            new_closing_time > market.closing_time,
+            ErrorCode::ClosingTimeNotLater
+        );
+
+        let previous_closing_time = market.closing_time;
+        market.closing_time = new_closing_time;
+
+        emit!(MarketExtended {
+            market: ctx.accounts.market.key(),
+            extended_by: ctx.accounts.authority.key(),
+            previous_closing_time,
+            new_closing_time,
+        });
+
+        msg!("Market {} extended from {} to {}", market.id, previous_closing_time, new_closing_time);
+        Ok(())
+    }
+
+    // Close betting ahead of schedule once the outcome is already known
+    pub fn close_market_early(ctx: Context<CloseMarketEarly>) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+        require!(
+            now < market.closing_time,
+            ErrorCode::MarketClosed
+        );
+
+        let original_closing_time = market.closing_time;
+        market.closing_time = now;
+
+        emit!(MarketClosedEarly {
+            market: ctx.accounts.market.key(),
+            resolver: ctx.accounts.resolver.key(),
+            original_closing_time,
+            closed_at: now,
+        });
+
+        msg!("Market {} closed early (was due at {})", market.id, original_closing_time);
+        Ok(())
+    }
+
+    // Cancel a market that can't be resolved cleanly; bettors get their stakes back
+    // and the creator bond is slashed
+    pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let global = &mut ctx.accounts.global;
+
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+
+        // Refunds are only defined for pool stakes
+        require!(
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
+        );
+
+        // No subsidy is paid on a cancelled market, so release its reservation
+        if market.virtual_liquidity > 0 && market.subsidy_settled == 0 {
+            global.subsidy_committed = global
+                .subsidy_committed
+                .saturating_sub(market.virtual_liquidity * 2);
+            market.subsidy_settled = 1;
+        }
+
+        market.set_status(MarketStatus::Cancelled);
+        market.settled_at = Clock::get()?.unix_timestamp;
+
+        emit!(MarketCancelled {
+            market: ctx.accounts.market.key(),
+            resolver: ctx.accounts.resolver.key(),
+            cancelled_at: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Market {} cancelled", market.id);
+        Ok(())
+    }
+
+    // Return the creator bond after a clean resolution, or send it to the treasury
+    // if the market was cancelled
+    pub fn release_creator_bond(ctx: Context<ReleaseCreatorBond>) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+
+        require!(
+            market.creator_bond > 0 && market.bond_released == 0,
+            ErrorCode::NothingToWithdraw
+        );
+
+        let recipient = match market.status() {
+            MarketStatus::Resolved => ctx.accounts.creator.to_account_info(),
+            // Cancellation is how an invalid market ends, so it forfeits the bond
+            MarketStatus::Cancelled => ctx.accounts.global.to_account_info(),
+            MarketStatus::Active => return err!(ErrorCode::MarketNotResolved),
+        };
+
+        // The bond sits in the market account above its rent-exempt minimum
+        **ctx.accounts.market.to_account_info().try_borrow_mut_lamports()? -= market.creator_bond;
+        **recipient.try_borrow_mut_lamports()? += market.creator_bond;
+        market.bond_released = 1;
+
+        msg!("Creator bond of {} lamports released to {}", market.creator_bond, recipient.key());
+        Ok(())
+    }
+
+    // Resolve market (call this after market closes)
+    pub fn resolve_market(
+        ctx: Context<ResolveMarket>,
+        result: BetSide,
+        evidence_hash: [u8; 32],
+        evidence_source: String,
+        observed_value: i64,
+        observed_value_decimals: u8,
+    ) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            evidence_source.len() <= MAX_EVIDENCE_SOURCE_LEN,
+            ErrorCode::EvidenceSourceTooLong
+        );
+
+        // Check if market is closed
+        require!(
+            now >= market.closing_time,
+            ErrorCode::MarketNotClosed
+        );
+
+        // Check if market is still active
+        require!(
+            market.status() == MarketStatus::Active,
+            ErrorCode::MarketNotActive
+        );
+
+        market.set_result(Some(result.clone()));
+        market.set_status(MarketStatus::Resolved);
+        market.settled_at = now;
+        ctx.accounts.market_metadata.evidence = Some(ResolutionEvidence {
+            hash: evidence_hash,
+            source: evidence_source.clone(),
+            observed_value,
+            observed_value_decimals,
+            resolved_at: now,
+        });
+
+        emit!(MarketResolved {
+            market: ctx.accounts.market.key(),
+            resolver: ctx.accounts.resolver.key(),
+            result: result.clone(),
+            rules_hash: market.rules_hash,
+            evidence_hash,
+            evidence_source,
+            observed_value,
+            observed_value_decimals,
+            resolved_at: now,
+        });
+
+        msg!("Market resolved by {} with result: {:?}", ctx.accounts.resolver.key(), result);
+        Ok(())
+    }
+
+    // Claim winnings for a specific bet
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let position = &mut ctx.accounts.position;
+        let bet = &ctx.accounts.bet;
+
+        // Check if market is resolved
+        require!(
+            market.status() == MarketStatus::Resolved,
+            ErrorCode::MarketNotResolved
+        );
+
+        // Per-bet claims only apply to parimutuel markets
+        require!(
+            market.pricing_mode() == PricingMode::Parimutuel,
+            ErrorCode::WrongPricingMode
+        );
+
+        require!(
+            !market.claim_window_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::ClaimWindowExpired
+        );
+
+        // Subsidized markets need the treasury shortfall in the vault first
+        require!(
+            market.virtual_liquidity == 0 || market.subsidy_settled != 0,
+            ErrorCode::SubsidyNotSettled
+        );
+
+        // Check if user bet on the winning side
+        require!(
+            market.result() == Some(bet.side),
+            ErrorCode::NotWinningBet
+        );
+
+        // Check if bet hasn't been claimed yet
+        require!(
+            !bet.claimed,
+            ErrorCode::AlreadyClaimed
+        );
+
+        // Exits may have reduced the position below this bet's original stake
+        let (position_amount, position_weighted) = match bet.side {
+            BetSide::Yes => (position.yes_amount, position.yes_weighted),
+            BetSide::No => (position.no_amount, position.no_weighted),
+        };
+        let claim_amount = bet.amount.min(position_amount);
+        let claim_weighted = bet.weighted_amount.min(position_weighted);
+        require!(claim_weighted > 0, ErrorCode::NoWinningsToClaim);
+
+        // Calculate winnings (proportional to weighted stake within winning pool)
+        let winnings = market.parimutuel_payout(&bet.side, claim_weighted);
+
+        // Transfer winnings to user
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+        );
+
+        token::transfer(cpi_ctx, winnings)?;
+
+        // Mark bet as claimed
+        let bet_account = &mut ctx.accounts.bet;
+        bet_account.claimed = true;
+
+        // Update position (reduce the claimed amount)
+        match bet.side {
+            BetSide::Yes => {
+                position.yes_amount = position.yes_amount.saturating_sub(claim_amount);
+                position.yes_weighted = position.yes_weighted.saturating_sub(claim_weighted);
+            }
+            BetSide::No => {
+                position.no_amount = position.no_amount.saturating_sub(claim_amount);
+                position.no_weighted = position.no_weighted.saturating_sub(claim_weighted);
+            }
+        }
+
+        msg!("Winnings claimed: {} tokens for bet amount: {}", winnings, claim_amount);
+        Ok(())
+    }
+
+    // Claim all winnings for a user's position in a market, closing the position
+    // and any of the user's Bet accounts passed as remaining accounts
+    pub fn claim_all_winnings<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimAllWinnings<'info>>,
+    ) -> Result<()> {
+        let mut market = ctx.accounts.market.load_mut()?;
+        let position = &mut ctx.accounts.position;
+
+        // Check if market is resolved (or cancelled, which refunds stakes)
+        require!(
+            market.status() == MarketStatus::Resolved || market.status() == MarketStatus::Cancelled,
+            ErrorCode::MarketNotResolved
+        );
+
+        require!(
+            !market.claim_window_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::ClaimWindowExpired
+        );
+
+        // Subsidized markets need the treasury shortfall in the vault first
+        require!(
+            market.virtual_liquidity == 0 || market.subsidy_settled != 0,
+            ErrorCode::SubsidyNotSettled
+        );
+
+        let mut total_winnings = 0u64;
+
+        if market.refunds_stakes() {
+            // Stakes on both sides are refunded (fees and exit penalties are not)
+            total_winnings = position.yes_amount + position.no_amount;
+            position.yes_amount = 0;
+            position.no_amount = 0;
+            position.yes_weighted = 0;
+            position.no_weighted = 0;
+        } else if let Some(result) = market.result() {
+            match result {
+                BetSide::Yes => {
+                    if position.yes_amount > 0 {
+                        total_winnings = match market.pricing_mode() {
+                            PricingMode::Parimutuel => {
+                                let weighted = position.yes_weighted;
+                                position.yes_weighted = 0;
+                                market.parimutuel_payout(&BetSide::Yes, weighted)
+                            }
+                            // Each winning LMSR share redeems one token
+                            PricingMode::Lmsr => {
+                                market.lmsr.yes_shares -= position.yes_amount;
+                                market.lmsr.collateral -= position.yes_amount;
+                                position.yes_amount
+                            }
+                            // Winning outcome shares redeem 1:1
+                            PricingMode::ConstantProduct => position.yes_amount,
+                        };
+                        position.yes_amount = 0;
+                    }
+                }
+                BetSide::No => {
+                    if position.no_amount > 0 {
+                        total_winnings = match market.pricing_mode() {
+                            PricingMode::Parimutuel => {
+                                let weighted = position.no_weighted;
+                                position.no_weighted = 0;
+                                market.parimutuel_payout(&BetSide::No, weighted)
+                            }
+                            PricingMode::Lmsr => {
+                                market.lmsr.no_shares -= position.no_amount;
+                                market.lmsr.collateral -= position.no_amount;
+                                position.no_amount
+                            }
+                            PricingMode::ConstantProduct => position.no_amount,
+                        };
+                        position.no_amount = 0;
+                    }
+                }
+            }
+        }
+
+        require!(total_winnings > 0, ErrorCode::NoWinningsToClaim);
+
+        market.open_positions -= 1;
+        drop(market);
+
+        // Transfer total winnings to user
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+        );
+
+        token::transfer(cpi_ctx, total_winnings)?;
+
+        let closed = close_bets(
+            ctx.remaining_accounts,
+            &ctx.accounts.market.key(),
+            &ctx.accounts.user.to_account_info(),
+        )?;
+
+        msg!("All winnings claimed: {} tokens ({} bet accounts closed)", total_winnings, closed);
+        Ok(())
+    }
+
+    // Close a settled position (including a losing one) and any of the user's Bet
+    // accounts passed as remaining accounts, returning their rent
+    pub fn close_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClosePosition<'info>>,
+    ) -> Result<()> {
+        // Unclaimed positions can be closed once the claim window is over
+        let mut market = ctx.accounts.market.load_mut()?;
+        require!(
+            ctx.accounts.position.is_settled(&market)
+                || market.claim_window_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::PositionNotSettled
+        );
+
+        market.open_positions -= 1;
+
+        let closed = close_bets(
+            ctx.remaining_accounts,
+            &ctx.accounts.market.key(),
+            &ctx.accounts.user.to_account_info(),
+        )?;
+
+        msg!("Position closed ({} bet accounts closed)", closed);
+        Ok(())
+    }
+
+    // Close a fully settled market: sweep leftover dust to the treasury, close the
+    // vault and return the rent of both accounts to the creator
+    pub fn finalize_market(ctx: Context<FinalizeMarket>) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+
+        require!(
+            market.status() == MarketStatus::Resolved || market.status() == MarketStatus::Cancelled,
+            ErrorCode::MarketNotResolved
+        );
+        require!(
+            market.open_positions == 0 || market.claim_window_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::MarketHasOpenPositions
+        );
+
+        // Vault funds that still belong to LPs, outcome-token holders or the LMSR creator aren't dust
+        require!(
+            market.amm.lp_supply == 0 && market.outcome_collateral == 0,
+            ErrorCode::MarketHasOpenPositions
+        );
+        require!(
+            market.lmsr.surplus(market.result().as_ref()) == 0,
+            ErrorCode::LmsrLiquidityNotWithdrawn
+        );
+        require!(
+            market.virtual_liquidity == 0 || market.subsidy_settled != 0,
+            ErrorCode::SubsidyNotSettled
+        );
+
+        // An unreleased bond is still in the market account and must not go to the creator
+        require!(
+            market.creator_bond == 0 || market.bond_released != 0,
+            ErrorCode::BondNotReleased
+        );
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        // Each payout rounds down by less than one base unit, so anything above one unit per
+        // bet (plus the creator seed) is unclaimed money for sweep_unclaimed, not dust.
+        // Exit penalties aren't refunded with stakes and go to the treasury too.
+        let mut max_dust = market.yes_bets + market.no_bets + 1;
+        if market.refunds_stakes() {
+            max_dust += market.exit_penalty_pool;
+        }
+        let dust = ctx.accounts.market_token_account.amount;
+        require!(dust <= max_dust, ErrorCode::DustAboveRoundingBound);
+        if dust > 0 {
+            let transfer_instruction = Transfer {
+                from: ctx.accounts.market_token_account.to_account_info(),
+                to: ctx.accounts.treasury_token_account.to_account_info(),
+                authority: ctx.accounts.market.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            );
+
+            token::transfer(cpi_ctx, dust)?;
+        }
+
+        let close_instruction = CloseAccount {
+            account: ctx.accounts.market_token_account.to_account_info(),
+            destination: ctx.accounts.creator.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_instruction,
+            signer,
+        );
+
+        token::close_account(cpi_ctx)?;
+
+        msg!("Market {} finalized ({} tokens of dust swept)", market.id, dust);
+        Ok(())
+    }
+
+    // After the claim window, move unclaimed funds to the insurance fund (or the treasury)
+    pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>) -> Result<()> {
+        let market = ctx.accounts.market.load()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            market.status() == MarketStatus::Resolved || market.status() == MarketStatus::Cancelled,
+            ErrorCode::MarketNotResolved
+        );
+        require!(
+            market.claim_window_expired(now),
+            ErrorCode::ClaimWindowOpen
+        );
+
+        // LP reserves, outcome-token collateral and the LMSR creator's surplus are not winnings
+        require!(
+            market.amm.lp_supply == 0,
+            ErrorCode::MarketHasOpenPositions
+        );
+        let amount = ctx
+            .accounts
+            .market_token_account
+            .amount
+            .saturating_sub(market.outcome_collateral)
+            .saturating_sub(market.lmsr.surplus(market.result().as_ref()));
+        require!(amount > 0, ErrorCode::NothingToWithdraw);
+
+        let market_id = market.id.to_le_bytes();
+        let seeds = &[MARKET_SEED.as_bytes(), market_id.as_ref(), &[market.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.market_token_account.to_account_info(),
+            to: ctx.accounts.destination_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_instruction,
+            signer,
+        );
+
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(UnclaimedFundsSwept {
+            market: ctx.accounts.market.key(),
+            destination: ctx.accounts.destination_token_account.key(),
+            amount,
+            unclaimed_positions: market.open_positions,
+            claim_deadline: market.settled_at + market.claim_window,
+            swept_at: now,
+        });
+
+        msg!("Swept {} unclaimed tokens from market {}", amount, market.id);
+        Ok(())
+    }
+}
+
+// Hand an unreleased creator bond back to the creator. Bonds are only slashed by
+// cancellation, which is limited to parimutuel markets, so a market leaving that
+// mode has nothing left to forfeit its bond for.
+fn return_creator_bond<'info>(
+    market: &mut Market,
+    market_info: &AccountInfo<'info>,
+    creator: &AccountInfo<'info>,
+) -> Result<()> {
+    if market.creator_bond == 0 || market.bond_released != 0 {
+        return Ok(());
+    }
+
+    **market_info.try_borrow_mut_lamports()? -= market.creator_bond;
+    **creator.try_borrow_mut_lamports()? += market.creator_bond;
+    market.bond_released = 1;
+
+    msg!("Creator bond of {} lamports returned: only parimutuel markets are bonded", market.creator_bond);
+    Ok(())
+}
+
+// Close Bet accounts belonging to `user` on `market`, returning the rent to the user.
+// Only call once the user's position is settled, since a closed bet can't be claimed.
+fn close_bets<'info>(
+    bets: &'info [AccountInfo<'info>],
+    market: &Pubkey,
+    user: &AccountInfo<'info>,
+) -> Result<u32> {
+    let mut closed = 0;
+    for info in bets {
+        let bet = Account::<Bet>::try_from(info)?;
+        require!(
+            bet.market == *market && bet.user == user.key(),
+            ErrorCode::BetAccountMismatch
+        );
+        bet.close(user.clone())?;
+        closed += 1;
+    }
+    Ok(closed)
+}
+
+// Account structures
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Global::INIT_SPACE,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobal<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        token::authority = global
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = treasury_token_account.mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasuryLamports<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct AddCreator<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CreatorEntry::INIT_SPACE,
+        seeds = [CREATOR_SEED.as_bytes(), creator.as_ref()],
+        bump
+    )]
+    pub creator_entry: Account<'info, CreatorEntry>,
+    #[account(
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveCreator<'info> {
+    #[account(
+        mut,
+        seeds = [CREATOR_SEED.as_bytes(), creator_entry.creator.as_ref()],
+        bump = creator_entry.bump,
+        close = authority
+    )]
+    pub creator_entry: Account<'info, CreatorEntry>,
+    #[account(
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    question: String,
+    description: String,
+    closing_time: i64,
+    required_token_mint: Pubkey,
+    required_token_symbol: String,
+    required_token_name: String,
+    resolver: ResolverKind,
+    rules_hash: [u8; 32],
+    rules_uri: String,
+    freeze_window: i64,
+    time_weight: TimeWeightCurve,
+    seed_amount: u64,
+    seed_yes_bps: u16,
+    min_bet: u64,
+    max_bet: u64,
+    max_position: u64,
+    max_pool_share_bps: u16,
+    holder_gate: HolderGate,
+    allowlist_root: [u8; 32],
+    claim_window: i64,
+    bet_record_mode: BetRecordMode,
+    metadata_uri: String,
+)]
+pub struct CreateMarket<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [MARKET_SEED.as_bytes(), global.market_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + MarketMetadata::space(&question, &description, &rules_uri, &metadata_uri),
+        seeds = [MARKET_METADATA_SEED.as_bytes(), market.key().as_ref()],
+        bump
+    )]
+    pub market_metadata: Account<'info, MarketMetadata>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    // Only needed while creation is permissioned
+    #[account(
+        seeds = [CREATOR_SEED.as_bytes(), creator.key().as_ref()],
+        bump = creator_entry.bump
+    )]
+    pub creator_entry: Option<Account<'info, CreatorEntry>>,
+    // Seed accounts, only needed when seed_amount > 0
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), creator.key().as_ref()],
+        bump
+    )]
+    pub creator_position: Option<Account<'info, Position>>,
+    #[account(
+        mut,
+        associated_token::mint = required_token_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = creator,
+        associated_token::mint = required_token_mint_account,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(address = required_token_mint)]
+    pub required_token_mint_account: Option<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, side: BetSide)]
+pub struct PlaceBet<'info> {
+    // Omitted on markets that record bets as events only
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [BET_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref(), &Clock::get()?.unix_timestamp.to_le_bytes()],
+        bump
+    )]
+    pub bet: Option<Account<'info, Bet>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    // Treasury token account: platform fees fund the subsidies it pays out
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = global
+    )]
+    pub platform_token_account: Account<'info, TokenAccount>,
+    // Holder-gate proof, only needed for gated markets
+    pub gate_token_account: Option<Account<'info, TokenAccount>>,
+    /// CHECK: NFT metadata account, verified against the Token Metadata program in HolderGate::verify
+    pub gate_metadata: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveMarket<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [MARKET_METADATA_SEED.as_bytes(), market.key().as_ref()],
+        bump = market_metadata.bump
+    )]
+    pub market_metadata: Account<'info, MarketMetadata>,
+    #[account(
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(
+        constraint = global.is_allowed_resolver(&market.load()?.resolver()) @ ErrorCode::ResolverNotAllowed,
+        constraint = market.load()?.resolver().signer(&global) == resolver.key() @ ErrorCode::Unauthorized
+    )]
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLmsr<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump,
+        has_one = creator @ ErrorCode::Unauthorized
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct BuyShares<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    // Treasury token account, owned by the global PDA
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = global
+    )]
+    pub platform_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SellShares<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLmsrLiquidity<'info> {
+    // Mutable: the withdrawal is deducted from the market maker's collateral
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump,
+        has_one = creator @ ErrorCode::Unauthorized
+    )]
+    pub market: AccountLoader<'info, Market>,
+    pub creator: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
 
-// Account structures
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct InitializeAmm<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump,
+        has_one = creator @ ErrorCode::Unauthorized
+    )]
+    pub market: AccountLoader<'info, Market>,
+    // Receives the creator bond back
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + LiquidityPosition::INIT_SPACE,
+        seeds = [LIQUIDITY_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub liquidity_position: Account<'info, LiquidityPosition>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+    #[account(
+        mut,
+        seeds = [LIQUIDITY_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = liquidity_position.bump
+    )]
+    pub liquidity_position: Account<'info, LiquidityPosition>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SwapCollateralForShares<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SwapSharesForCollateral<'info> {
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct CreateOutcomeMints<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [YES_MINT_SEED.as_bytes(), market.key().as_ref()],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market
+    )]
+    pub yes_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [NO_MINT_SEED.as_bytes(), market.key().as_ref()],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market
+    )]
+    pub no_mint: Account<'info, Mint>,
+    #[account(address = market.load()?.required_token_mint @ ErrorCode::WrongToken)]
+    pub collateral_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct MintOutcomeTokens<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(mut, address = market.load()?.yes_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub yes_mint: Account<'info, Mint>,
+    #[account(mut, address = market.load()?.no_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub no_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = yes_mint,
+        associated_token::authority = user
+    )]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = no_mint,
+        associated_token::authority = user
+    )]
+    pub user_no_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BurnOutcomeTokens<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(mut, address = market.load()?.yes_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub yes_mint: Account<'info, Mint>,
+    #[account(mut, address = market.load()?.no_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub no_mint: Account<'info, Mint>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = yes_mint,
+        token::authority = user
+    )]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = no_mint,
+        token::authority = user
+    )]
+    pub user_no_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemOutcomeTokens<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    // The winning outcome mint, or either outcome mint on a cancelled market
+    #[account(mut)]
+    pub winning_mint: Account<'info, Mint>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = winning_mint,
+        token::authority = user
+    )]
+    pub user_outcome_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct CreateOrderBook<'info> {
+    #[account(
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + OrderBook::INIT_SPACE,
+        seeds = [ORDER_BOOK_SEED.as_bytes(), market.key().as_ref()],
+        bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + EventQueue::INIT_SPACE,
+        seeds = [EVENT_QUEUE_SEED.as_bytes(), market.key().as_ref()],
+        bump
+    )]
+    pub event_queue: AccountLoader<'info, EventQueue>,
+    #[account(address = market.load()?.yes_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub yes_mint: Account<'info, Mint>,
+    #[account(address = market.load()?.required_token_mint @ ErrorCode::WrongToken)]
+    pub collateral_mint: Account<'info, Mint>,
     #[account(
         init,
-        payer = authority,
-        space = 8 + Global::INIT_SPACE,
-        seeds = [GLOBAL_SEED.as_bytes()],
-        bump
+        payer = payer,
+        associated_token::mint = yes_mint,
+        associated_token::authority = order_book
     )]
-    pub global: Account<'info, Global>,
+    pub base_vault: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = order_book
+    )]
+    pub quote_vault: Account<'info, TokenAccount>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub payer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CreateMarket<'info> {
+pub struct PlaceOrder<'info> {
     #[account(
-        init,
-        payer = creator,
-        space = 8 + Market::INIT_SPACE,
-        seeds = [MARKET_SEED.as_bytes(), global.market_count.to_le_bytes().as_ref()],
-        bump
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
     )]
-    pub market: Account<'info, Market>,
+    pub market: AccountLoader<'info, Market>,
     #[account(
         mut,
-        seeds = [GLOBAL_SEED.as_bytes()],
-        bump = global.bump
+        seeds = [ORDER_BOOK_SEED.as_bytes(), market.key().as_ref()],
+        bump = order_book.load()?.bump
     )]
-    pub global: Account<'info, Global>,
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        constraint = event_queue.load()?.market == market.key() @ ErrorCode::OrderBookMismatch
+    )]
+    pub event_queue: AccountLoader<'info, EventQueue>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + OpenOrders::INIT_SPACE,
+        seeds = [OPEN_ORDERS_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
     #[account(mut)]
-    pub creator: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(address = market.load()?.yes_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub yes_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = yes_mint,
+        associated_token::authority = user
+    )]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = yes_mint,
+        associated_token::authority = order_book
+    )]
+    pub base_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = order_book
+    )]
+    pub quote_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, side: BetSide)]
-pub struct PlaceBet<'info> {
+pub struct CancelOrder<'info> {
     #[account(
-        init,
-        payer = user,
-        space = 8 + Bet::INIT_SPACE,
-        seeds = [BET_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref(), &Clock::get()?.unix_timestamp.to_le_bytes()],
-        bump
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
     )]
-    pub bet: Account<'info, Bet>,
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED.as_bytes(), market.key().as_ref()],
+        bump = order_book.load()?.bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [OPEN_ORDERS_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConsumeEvents<'info> {
+    #[account(
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        constraint = event_queue.load()?.market == market.key() @ ErrorCode::OrderBookMismatch
+    )]
+    pub event_queue: AccountLoader<'info, EventQueue>,
+}
+
+#[derive(Accounts)]
+pub struct SettleFunds<'info> {
+    #[account(
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        seeds = [ORDER_BOOK_SEED.as_bytes(), market.key().as_ref()],
+        bump = order_book.load()?.bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [OPEN_ORDERS_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(address = market.load()?.yes_mint @ ErrorCode::OutcomeMintMismatch)]
+    pub yes_mint: Account<'info, Mint>,
     #[account(
         init_if_needed,
         payer = user,
-        space = 8 + Position::INIT_SPACE,
+        associated_token::mint = yes_mint,
+        associated_token::authority = user
+    )]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = yes_mint,
+        associated_token::authority = order_book
+    )]
+    pub base_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = order_book
+    )]
+    pub quote_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExitPosition<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
         seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
-        bump
+        bump = position.bump
     )]
     pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendMarket<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(
+        constraint = authority.key() == market.load()?.creator || authority.key() == global.authority @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseMarketEarly<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(
+        constraint = global.is_allowed_resolver(&market.load()?.resolver()) @ ErrorCode::ResolverNotAllowed,
+        constraint = market.load()?.resolver().signer(&global) == resolver.key() @ ErrorCode::Unauthorized
+    )]
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMarket<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    #[account(
+        constraint = global.is_allowed_resolver(&market.load()?.resolver()) @ ErrorCode::ResolverNotAllowed,
+        constraint = market.load()?.resolver().signer(&global) == resolver.key() @ ErrorCode::Unauthorized
+    )]
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseCreatorBond<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump,
+        has_one = creator
+    )]
+    pub market: AccountLoader<'info, Market>,
+    // Treasury: receives a slashed bond
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
+    )]
+    pub global: Account<'info, Global>,
+    /// CHECK: Receives the bond after a clean resolution
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVirtualLiquidity<'info> {
+    #[account(
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleSubsidy<'info> {
     #[account(
         mut,
-        seeds = [MARKET_SEED.as_bytes(), market.id.to_le_bytes().as_ref()],
-        bump = market.bump
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
     )]
-    pub market: Account<'info, Market>,
+    pub market: AccountLoader<'info, Market>,
     #[account(
         mut,
         seeds = [GLOBAL_SEED.as_bytes()],
         bump = global.bump
     )]
     pub global: Account<'info, Global>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = global
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
+    )]
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [BET_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref(), &bet.timestamp.to_le_bytes()],
+        bump = bet.bump,
+        close = user
+    )]
+    pub bet: Account<'info, Bet>,
     #[account(mut)]
     pub user: Signer<'info>,
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
+        associated_token::mint = market.load()?.required_token_mint,
         associated_token::authority = user
     )]
     pub user_token_account: Account<'info, TokenAccount>,
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
+        associated_token::mint = market.load()?.required_token_mint,
         associated_token::authority = market
     )]
     pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAllWinnings<'info> {
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
-        associated_token::authority = global.authority
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
     )]
-    pub platform_token_account: Account<'info, TokenAccount>,
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+        close = user
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ResolveMarket<'info> {
+pub struct ClosePosition<'info> {
+    // Mutable: closing a position decrements the market's open position count
     #[account(
         mut,
-        seeds = [MARKET_SEED.as_bytes(), market.id.to_le_bytes().as_ref()],
-        bump = market.bump,
-        constraint = market.creator == resolver.key()
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
     )]
-    pub market: Account<'info, Market>,
-    pub resolver: Signer<'info>,
+    pub market: AccountLoader<'info, Market>,
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+        close = user
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimWinnings<'info> {
+pub struct FinalizeMarket<'info> {
     #[account(
-        seeds = [MARKET_SEED.as_bytes(), market.id.to_le_bytes().as_ref()],
-        bump = market.bump
+        mut,
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump,
+        has_one = creator,
+        close = creator
     )]
-    pub market: Account<'info, Market>,
+    pub market: AccountLoader<'info, Market>,
     #[account(
         mut,
-        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
-        bump = position.bump
+        seeds = [MARKET_METADATA_SEED.as_bytes(), market.key().as_ref()],
+        bump = market_metadata.bump,
+        close = creator
     )]
-    pub position: Account<'info, Position>,
+    pub market_metadata: Account<'info, MarketMetadata>,
     #[account(
-        mut,
-        seeds = [BET_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref(), &bet.timestamp.to_le_bytes()],
-        bump = bet.bump
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump
     )]
-    pub bet: Account<'info, Bet>,
+    pub global: Account<'info, Global>,
+    /// CHECK: Receives the market, metadata and vault rent
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub creator: UncheckedAccount<'info>,
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
-        associated_token::authority = user
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub market_token_account: Account<'info, TokenAccount>,
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
-        associated_token::authority = market
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = global
     )]
-    pub market_token_account: Account<'info, TokenAccount>,
+    pub treasury_token_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimAllWinnings<'info> {
+pub struct SweepUnclaimed<'info> {
     #[account(
-        seeds = [MARKET_SEED.as_bytes(), market.id.to_le_bytes().as_ref()],
-        bump = market.bump
+        seeds = [MARKET_SEED.as_bytes(), market.load()?.id.to_le_bytes().as_ref()],
+        bump = market.load()?.bump
     )]
-    pub market: Account<'info, Market>,
+    pub market: AccountLoader<'info, Market>,
     #[account(
-        mut,
-        seeds = [POSITION_SEED.as_bytes(), market.key().as_ref(), user.key().as_ref()],
-        bump = position.bump
+        seeds = [GLOBAL_SEED.as_bytes()],
+        bump = global.bump,
+        has_one = authority @ ErrorCode::Unauthorized
     )]
-    pub position: Account<'info, Position>,
-    #[account(mut)]
-    pub user: Signer<'info>,
+    pub global: Account<'info, Global>,
+    pub authority: Signer<'info>,
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
-        associated_token::authority = user
+        associated_token::mint = market.load()?.required_token_mint,
+        associated_token::authority = market
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub market_token_account: Account<'info, TokenAccount>,
+    // Token account of the insurance fund, or of the global PDA (treasury) if none is set
     #[account(
         mut,
-        associated_token::mint = market.required_token_mint,
-        associated_token::authority = market
+        constraint = destination_token_account.mint == market.load()?.required_token_mint @ ErrorCode::WrongToken,
+        constraint = destination_token_account.owner == global.sweep_destination(&global.key()) @ ErrorCode::Unauthorized
     )]
-    pub market_token_account: Account<'info, TokenAccount>,
+    pub destination_token_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -496,34 +3996,468 @@ pub struct Global {
     pub market_count: u64,
     pub total_volume: u64,
     pub total_fees_collected: u64,
+    pub resolvers: Vec<Pubkey>,
+    pub oracle: Pubkey,
+    // Treasury subsidy limit for virtual liquidity, and the amount currently reserved
+    pub subsidy_cap: u64,
+    pub subsidy_committed: u64,
+    // Bet limits for markets that don't set their own
+    pub default_min_bet: u64,
+    pub default_max_bet: u64,
+    // Pool size a side is measured against for the pool share cap until its real pool is deeper
+    pub min_side_depth: u64,
+    // Lamports charged per market: a fee to the treasury and a refundable bond
+    pub creation_fee: u64,
+    pub creator_bond: u64,
+    pub creation_mode: CreationMode,
+    // Receives unclaimed winnings after a market's claim window; default key = treasury
+    pub insurance_fund: Pubkey,
     pub bump: u8,
 }
 
 impl Global {
-    pub const INIT_SPACE: usize = 32 + 8 + 8 + 8 + 1;
+    pub const INIT_SPACE: usize = 32 + 8 + 8 + 8 + (4 + 32 * MAX_RESOLVERS) + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 32 + 1;
+
+    // Owner of the token account that receives swept funds
+    pub fn sweep_destination(&self, global_key: &Pubkey) -> Pubkey {
+        if self.insurance_fund == Pubkey::default() {
+            *global_key
+        } else {
+            self.insurance_fund
+        }
+    }
+
+    // Whether a market may designate this resolver
+    pub fn is_allowed_resolver(&self, resolver: &ResolverKind) -> bool {
+        match resolver {
+            ResolverKind::Key { resolver } => self.resolvers.contains(resolver),
+            ResolverKind::Council { council } => self.resolvers.contains(council),
+            ResolverKind::OracleOnly => self.oracle != Pubkey::default(),
+        }
+    }
 }
 
-#[account]
+// Zero-copy so place_bet and friends read and write fields in place instead of
+// deserializing the whole account. Enums are stored as u8 tags behind accessors,
+// short text as fixed-size byte arrays; long text lives in MarketMetadata.
+#[account(zero_copy)]
 pub struct Market {
     pub id: u64,
-    pub creator: Pubkey,
-    pub question: String,
-    pub description: String,
+    pub created_at: i64,
     pub closing_time: i64,
-    pub required_token_mint: Pubkey,
-    pub required_token_symbol: String,
-    pub required_token_name: String,
-    pub status: MarketStatus,
+    // Closing time at creation; time weights decay towards it even if the market is extended
+    pub weight_closing_time: i64,
+    pub freeze_window: i64,
+    // Bet size limits in base units of required_token_mint (max 0 = no limit)
+    pub min_bet: u64,
+    pub max_bet: u64,
+    // Per-user cap on total position size (0 = no cap)
+    pub max_position: u64,
+    pub holder_gate_min_amount: u64,
+    // Seconds after settlement that winnings stay claimable (0 = forever)
+    pub claim_window: i64,
+    pub settled_at: i64,
+    // Number of bets placed so far; BetPlaced events carry it as a sequence number
+    pub bet_sequence: u64,
     pub yes_pool: u64,
     pub no_pool: u64,
+    // Time-weighted stakes; payouts are shared in proportion to these
+    pub yes_weighted_pool: u64,
+    pub no_weighted_pool: u64,
     pub yes_bets: u64,
     pub no_bets: u64,
-    pub result: Option<BetSide>,
+    pub exit_penalty_pool: u64,
+    // Collateral backing outstanding outcome tokens
+    pub outcome_collateral: u64,
+    // Creator liquidity deposited at creation, held in the creator's position
+    pub seed_amount: u64,
+    // Treasury-backed liquidity added to both pools for odds and payouts, never paid out itself
+    pub virtual_liquidity: u64,
+    // Lamports escrowed in this account at creation, returned or slashed once settled
+    pub creator_bond: u64,
+    // Position accounts not yet closed; the market can be finalized once this is zero
+    pub open_positions: u64,
+    pub lmsr: LmsrState,
+    pub amm: AmmState,
+    pub creator: Pubkey,
+    pub resolver_key: Pubkey,
+    pub rules_hash: [u8; 32],
+    pub holder_gate_key: Pubkey,
+    // Merkle root of wallets allowed to bet; all zeros for a public market
+    pub allowlist_root: [u8; 32],
+    // SHA-256 of the off-chain metadata document; all zeros when there is none
+    pub metadata_hash: [u8; 32],
+    pub required_token_mint: Pubkey,
+    pub yes_mint: Pubkey,
+    pub no_mint: Pubkey,
+    // Zero-padded UTF-8
+    pub required_token_symbol: [u8; MAX_TOKEN_SYMBOL_LEN],
+    pub required_token_name: [u8; MAX_TOKEN_NAME_LEN],
+    pub time_weight_max_bonus_bps: u16,
+    // Per-user cap on share of one side's pool (0 = no cap)
+    pub max_pool_share_bps: u16,
+    // Tags for resolver(), time_weight(), holder_gate(), bet_record_mode(), status(),
+    // pricing_mode() and result()
+    pub resolver_kind: u8,
+    pub time_weight_curve: u8,
+    pub holder_gate_kind: u8,
+    pub bet_record_mode: u8,
+    pub status: u8,
+    pub pricing_mode: u8,
+    pub result: u8,
+    // 0 = false, 1 = true
+    pub subsidy_settled: u8,
+    pub bond_released: u8,
     pub bump: u8,
+    pub _padding: [u8; 4],
 }
 
 impl Market {
-    pub const INIT_SPACE: usize = 8 + 32 + 32 + 4 + 200 + 4 + 200 + 8 + 32 + 4 + 20 + 4 + 50 + 1 + 8 + 8 + 8 + 8 + 1 + 1 + 1;
+    pub const INIT_SPACE: usize = 8 * 24 + LmsrState::INIT_SPACE + AmmState::INIT_SPACE + 32 * 9
+        + MAX_TOKEN_SYMBOL_LEN + MAX_TOKEN_NAME_LEN + 2 * 2 + 10 + 4;
+
+    pub fn resolver(&self) -> ResolverKind {
+        match self.resolver_kind {
+            0 => ResolverKind::Key { resolver: self.resolver_key },
+            1 => ResolverKind::Council { council: self.resolver_key },
+            _ => ResolverKind::OracleOnly,
+        }
+    }
+
+    pub fn set_resolver(&mut self, resolver: &ResolverKind) {
+        (self.resolver_kind, self.resolver_key) = match resolver {
+            ResolverKind::Key { resolver } => (0, *resolver),
+            ResolverKind::Council { council } => (1, *council),
+            ResolverKind::OracleOnly => (2, Pubkey::default()),
+        };
+    }
+
+    pub fn time_weight(&self) -> TimeWeightCurve {
+        let max_bonus_bps = self.time_weight_max_bonus_bps;
+        match self.time_weight_curve {
+            1 => TimeWeightCurve::Linear { max_bonus_bps },
+            2 => TimeWeightCurve::Quadratic { max_bonus_bps },
+            _ => TimeWeightCurve::Flat,
+        }
+    }
+
+    pub fn set_time_weight(&mut self, time_weight: &TimeWeightCurve) {
+        self.time_weight_curve = match time_weight {
+            TimeWeightCurve::Flat => 0,
+            TimeWeightCurve::Linear { .. } => 1,
+            TimeWeightCurve::Quadratic { .. } => 2,
+        };
+        self.time_weight_max_bonus_bps = time_weight.max_bonus_bps();
+    }
+
+    pub fn holder_gate(&self) -> HolderGate {
+        match self.holder_gate_kind {
+            1 => HolderGate::TokenBalance {
+                mint: self.holder_gate_key,
+                min_amount: self.holder_gate_min_amount,
+            },
+            2 => HolderGate::NftCollection { collection: self.holder_gate_key },
+            _ => HolderGate::Open,
+        }
+    }
+
+    pub fn set_holder_gate(&mut self, holder_gate: &HolderGate) {
+        (self.holder_gate_kind, self.holder_gate_key, self.holder_gate_min_amount) = match holder_gate {
+            HolderGate::Open => (0, Pubkey::default(), 0),
+            HolderGate::TokenBalance { mint, min_amount } => (1, *mint, *min_amount),
+            HolderGate::NftCollection { collection } => (2, *collection, 0),
+        };
+    }
+
+    pub fn bet_record_mode(&self) -> BetRecordMode {
+        match self.bet_record_mode {
+            1 => BetRecordMode::EventOnly,
+            _ => BetRecordMode::Account,
+        }
+    }
+
+    pub fn set_bet_record_mode(&mut self, bet_record_mode: &BetRecordMode) {
+        self.bet_record_mode = match bet_record_mode {
+            BetRecordMode::Account => 0,
+            BetRecordMode::EventOnly => 1,
+        };
+    }
+
+    pub fn status(&self) -> MarketStatus {
+        match self.status {
+            1 => MarketStatus::Resolved,
+            2 => MarketStatus::Cancelled,
+            _ => MarketStatus::Active,
+        }
+    }
+
+    pub fn set_status(&mut self, status: MarketStatus) {
+        self.status = match status {
+            MarketStatus::Active => 0,
+            MarketStatus::Resolved => 1,
+            MarketStatus::Cancelled => 2,
+        };
+    }
+
+    pub fn pricing_mode(&self) -> PricingMode {
+        match self.pricing_mode {
+            1 => PricingMode::Lmsr,
+            2 => PricingMode::ConstantProduct,
+            _ => PricingMode::Parimutuel,
+        }
+    }
+
+    pub fn set_pricing_mode(&mut self, pricing_mode: PricingMode) {
+        self.pricing_mode = match pricing_mode {
+            PricingMode::Parimutuel => 0,
+            PricingMode::Lmsr => 1,
+            PricingMode::ConstantProduct => 2,
+        };
+    }
+
+    pub fn result(&self) -> Option<BetSide> {
+        match self.result {
+            1 => Some(BetSide::Yes),
+            2 => Some(BetSide::No),
+            _ => None,
+        }
+    }
+
+    pub fn set_result(&mut self, result: Option<BetSide>) {
+        self.result = match result {
+            None => 0,
+            Some(BetSide::Yes) => 1,
+            Some(BetSide::No) => 2,
+        };
+    }
+
+    pub fn symbol(&self) -> &str {
+        fixed_str(&self.required_token_symbol)
+    }
+
+    // Whether `document` is the metadata document this market committed to
+    pub fn metadata_matches(&self, document: &[u8]) -> bool {
+        self.metadata_hash != [0u8; 32]
+            && metadata_document::content_hash(document) == self.metadata_hash
+    }
+
+    // Whether positions are refunded their stakes instead of paid out: on cancellation,
+    // or when a parimutuel market resolves to a side nobody bet on, which would
+    // otherwise leave the losing pool with no one to claim it
+    pub fn refunds_stakes(&self) -> bool {
+        match (self.status(), self.result()) {
+            (MarketStatus::Cancelled, _) => true,
+            (MarketStatus::Resolved, Some(result)) => {
+                self.pricing_mode() == PricingMode::Parimutuel
+                    && match result {
+                        BetSide::Yes => self.yes_weighted_pool == 0,
+                        BetSide::No => self.no_weighted_pool == 0,
+                    }
+            }
+            _ => false,
+        }
+    }
+
+    // Whether claims have closed for good
+    pub fn claim_window_expired(&self, now: i64) -> bool {
+        self.claim_window > 0
+            && self.status() != MarketStatus::Active
+            && now >= self.settled_at.saturating_add(self.claim_window)
+    }
+
+    // Whether betting is restricted to a Merkle allowlist
+    pub fn is_private(&self) -> bool {
+        self.allowlist_root != [0u8; 32]
+    }
+
+    // Everything paid out to the winning side
+    pub fn total_pool(&self) -> u64 {
+        self.yes_pool + self.no_pool + self.exit_penalty_pool
+    }
+
+    // Stake weight in basis points for a bet placed at `now` (10000 = 1x)
+    pub fn time_weight_bps(&self, now: i64) -> u64 {
+        // Measured against the original closing time so every bet shares one scale
+        let duration = self.weight_closing_time - self.created_at;
+        if duration <= 0 {
+            return 10000;
+        }
+        let remaining = (self.weight_closing_time - now).clamp(0, duration) as u128;
+        let duration = duration as u128;
+
+        let bonus = match self.time_weight() {
+            TimeWeightCurve::Flat => 0,
+            TimeWeightCurve::Linear { max_bonus_bps } => max_bonus_bps as u128 * remaining / duration,
+            TimeWeightCurve::Quadratic { max_bonus_bps } => {
+                max_bonus_bps as u128 * remaining * remaining / (duration * duration)
+            }
+        };
+        10000 + bonus as u64
+    }
+
+    // Parimutuel payout for `weighted` stake on the winning `side`, counting virtual liquidity
+    pub fn parimutuel_payout(&self, side: &BetSide, weighted: u64) -> u64 {
+        let side_pool = match side {
+            BetSide::Yes => self.yes_weighted_pool,
+            BetSide::No => self.no_weighted_pool,
+        } as u128 + self.virtual_liquidity as u128;
+        let payout_pool = self.total_pool() as u128 + 2 * self.virtual_liquidity as u128;
+
+        (payout_pool * weighted as u128 / side_pool) as u64
+    }
+
+    // Tokens the winners are owed beyond what the real pools hold
+    pub fn subsidy_shortfall(&self) -> u64 {
+        let Some(result) = self.result() else {
+            return 0;
+        };
+        let winning_pool = match result {
+            BetSide::Yes => self.yes_weighted_pool,
+            BetSide::No => self.no_weighted_pool,
+        };
+        self.parimutuel_payout(&result, winning_pool)
+            .saturating_sub(self.total_pool())
+    }
+
+    // Implied payout per 1x-weighted token staked on `side`, in basis points (10000 = 1x)
+    pub fn payout_multiplier_bps(&self, side: &BetSide) -> u64 {
+        let side_pool = match side {
+            BetSide::Yes => self.yes_weighted_pool,
+            BetSide::No => self.no_weighted_pool,
+        } + self.virtual_liquidity;
+        if side_pool == 0 {
+            return 0;
+        }
+        let payout_pool = self.total_pool() as u128 + 2 * self.virtual_liquidity as u128;
+        (payout_pool * 10000 / side_pool as u128) as u64
+    }
+}
+
+// Long-form market text, kept out of the zero-copy Market account
+#[account]
+pub struct MarketMetadata {
+    pub market: Pubkey,
+    pub question: String,
+    pub description: String,
+    pub rules_uri: String,
+    // Where the off-chain metadata document lives; its hash is on the market (see metadata_document)
+    pub metadata_uri: String,
+    pub evidence: Option<ResolutionEvidence>,
+    pub bump: u8,
+}
+
+impl MarketMetadata {
+    // Sized to the market's own text, with room reserved for the resolution evidence
+    pub fn space(question: &str, description: &str, rules_uri: &str, metadata_uri: &str) -> usize {
+        32 + (4 + question.len())
+            + (4 + description.len())
+            + (4 + rules_uri.len())
+            + (4 + metadata_uri.len())
+            + (1 + ResolutionEvidence::INIT_SPACE)
+            + 1
+    }
+
+    pub fn metadata_uri(&self) -> Option<&str> {
+        Some(self.metadata_uri.as_str()).filter(|uri| !uri.is_empty())
+    }
+}
+
+// Copy `text` into a zero-padded fixed-size field; callers check the length first
+pub fn fixed_bytes<const N: usize>(text: &str) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    let len = text.len().min(N);
+    bytes[..len].copy_from_slice(&text.as_bytes()[..len]);
+    bytes
+}
+
+// Text stored in a zero-padded fixed-size field
+pub fn fixed_str(bytes: &[u8]) -> &str {
+    let len = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+    std::str::from_utf8(&bytes[..len]).unwrap_or_default()
+}
+
+// LMSR market maker state (only used when pricing_mode is Lmsr)
+#[zero_copy]
+#[derive(Default, PartialEq, Eq)]
+pub struct LmsrState {
+    // Liquidity parameter b, in token base units
+    pub liquidity: u64,
+    // Outstanding shares per side
+    pub yes_shares: u64,
+    pub no_shares: u64,
+    // Creator funding deposited at initialization
+    pub funding: u64,
+    // Vault tokens belonging to the market maker: funding plus net trading, less redemptions
+    pub collateral: u64,
+}
+
+impl LmsrState {
+    pub const INIT_SPACE: usize = 8 + 8 + 8 + 8 + 8;
+
+    // (shares of `side`, shares of the other side)
+    pub fn quantities(&self, side: &BetSide) -> (u64, u64) {
+        match side {
+            BetSide::Yes => (self.yes_shares, self.no_shares),
+            BetSide::No => (self.no_shares, self.yes_shares),
+        }
+    }
+
+    pub fn price_yes_bps(&self) -> u16 {
+        lmsr::price_yes_bps(self.yes_shares, self.no_shares, self.liquidity)
+    }
+
+    // Collateral left for the creator once every winning share is redeemable
+    pub fn surplus(&self, result: Option<&BetSide>) -> u64 {
+        let outstanding = match result {
+            Some(BetSide::Yes) => self.yes_shares,
+            Some(BetSide::No) => self.no_shares,
+            None => 0,
+        };
+        self.collateral.saturating_sub(outstanding)
+    }
+}
+
+// Constant-product AMM state (only used when pricing_mode is ConstantProduct)
+#[zero_copy]
+#[derive(Default, PartialEq, Eq)]
+pub struct AmmState {
+    // Outcome shares held by the pool
+    pub yes_reserve: u64,
+    pub no_reserve: u64,
+    // Total LP shares issued
+    pub lp_supply: u64,
+    pub lp_fee_basis_points: u16,
+    pub _padding: [u8; 6],
+}
+
+impl AmmState {
+    pub const INIT_SPACE: usize = 8 + 8 + 8 + 2 + 6;
+
+    // (reserve of `side`, reserve of the other side)
+    pub fn reserves(&self, side: &BetSide) -> (u64, u64) {
+        match side {
+            BetSide::Yes => (self.yes_reserve, self.no_reserve),
+            BetSide::No => (self.no_reserve, self.yes_reserve),
+        }
+    }
+
+    pub fn price_yes_bps(&self) -> u16 {
+        cpmm::price_yes_bps(self.yes_reserve, self.no_reserve)
+    }
+}
+
+// On-chain record of what a resolution was based on
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ResolutionEvidence {
+    pub hash: [u8; 32],
+    pub source: String,
+    pub observed_value: i64,
+    pub observed_value_decimals: u8,
+    pub resolved_at: i64,
+}
+
+impl ResolutionEvidence {
+    pub const INIT_SPACE: usize = 32 + (4 + MAX_EVIDENCE_SOURCE_LEN) + 8 + 1 + 8;
 }
 
 #[account]
@@ -532,11 +4466,73 @@ pub struct Position {
     pub user: Pubkey,
     pub yes_amount: u64,
     pub no_amount: u64,
+    pub yes_bets: u64,
+    pub no_bets: u64,
+    pub yes_weighted: u64,
+    pub no_weighted: u64,
     pub bump: u8,
 }
 
 impl Position {
-    pub const INIT_SPACE: usize = 32 + 32 + 8 + 8 + 1;
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    // Whether nothing more can be paid out on this position
+    pub fn is_settled(&self, market: &Market) -> bool {
+        match market.status() {
+            MarketStatus::Active => false,
+            MarketStatus::Cancelled => self.yes_amount == 0 && self.no_amount == 0,
+            // Nobody backed the result, so both sides are refunded
+            MarketStatus::Resolved if market.refunds_stakes() => {
+                self.yes_amount == 0 && self.no_amount == 0
+            }
+            MarketStatus::Resolved => match market.result() {
+                Some(BetSide::Yes) => self.yes_amount == 0,
+                Some(BetSide::No) => self.no_amount == 0,
+                None => false,
+            },
+        }
+    }
+}
+
+// Allowlist entry letting `creator` create markets while creation is permissioned
+#[account]
+pub struct CreatorEntry {
+    pub creator: Pubkey,
+    pub bump: u8,
+}
+
+impl CreatorEntry {
+    pub const INIT_SPACE: usize = 32 + 1;
+}
+
+#[account]
+pub struct LiquidityPosition {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub lp_shares: u64,
+    pub bump: u8,
+}
+
+impl LiquidityPosition {
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 1;
+}
+
+// Per-user order book balances; free funds are withdrawn with settle_funds
+#[account]
+pub struct OpenOrders {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    // YES outcome tokens
+    pub base_free: u64,
+    pub base_locked: u64,
+    // Collateral
+    pub quote_free: u64,
+    pub quote_locked: u64,
+    pub bump: u8,
+}
+
+impl OpenOrders {
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 8 + 8 + 8 + 1;
 }
 
 #[account]
@@ -544,6 +4540,7 @@ pub struct Bet {
     pub market: Pubkey,
     pub user: Pubkey,
     pub amount: u64,
+    pub weighted_amount: u64,
     pub side: BetSide,
     pub timestamp: i64,
     pub claimed: bool,
@@ -551,23 +4548,200 @@ pub struct Bet {
 }
 
 impl Bet {
-    pub const INIT_SPACE: usize = 32 + 32 + 8 + 1 + 8 + 1 + 1;
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 8 + 1 + 8 + 1 + 1;
 }
 
 // Enums
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum MarketStatus {
     Active,
     Resolved,
     Cancelled,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BetSide {
     Yes,
     No,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PricingMode {
+    // Shared YES/NO pools, winners split the total pool
+    Parimutuel,
+    // Logarithmic market scoring rule, shares priced by the cost function
+    Lmsr,
+    // Constant-product AMM over YES/NO outcome-token reserves
+    ConstantProduct,
+}
+
+// How place_bet records individual bets
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum BetRecordMode {
+    // A Bet account per wager, claimable with claim_winnings
+    Account,
+    // Only the Position and a BetPlaced event; claim with claim_all_winnings
+    EventOnly,
+}
+
+// Who may create markets
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CreationMode {
+    // Only wallets with a CreatorEntry
+    Permissioned,
+    // Anyone
+    Permissionless,
+}
+
+// How much extra weight early bets get, decaying to 1x at the original closing_time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum TimeWeightCurve {
+    // Every bet weighs the same
+    Flat,
+    // Bonus falls linearly with time remaining
+    Linear { max_bonus_bps: u16 },
+    // Bonus falls with the square of time remaining, favouring the earliest bets
+    Quadratic { max_bonus_bps: u16 },
+}
+
+impl TimeWeightCurve {
+    pub fn max_bonus_bps(&self) -> u16 {
+        match self {
+            TimeWeightCurve::Flat => 0,
+            TimeWeightCurve::Linear { max_bonus_bps } => *max_bonus_bps,
+            TimeWeightCurve::Quadratic { max_bonus_bps } => *max_bonus_bps,
+        }
+    }
+}
+
+// Who is allowed to resolve a market
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum ResolverKind {
+    // A single allowlisted key
+    Key { resolver: Pubkey },
+    // An allowlisted council (multisig / governance) account
+    Council { council: Pubkey },
+    // Only the oracle configured on the global state
+    OracleOnly,
+}
+
+impl ResolverKind {
+    // The key that must sign resolve_market
+    pub fn signer(&self, global: &Global) -> Pubkey {
+        match self {
+            ResolverKind::Key { resolver } => *resolver,
+            ResolverKind::Council { council } => *council,
+            ResolverKind::OracleOnly => global.oracle,
+        }
+    }
+}
+
+// Events
+#[event]
+pub struct MarketResolved {
+    pub market: Pubkey,
+    pub resolver: Pubkey,
+    pub result: BetSide,
+    pub rules_hash: [u8; 32],
+    pub evidence_hash: [u8; 32],
+    pub evidence_source: String,
+    pub observed_value: i64,
+    pub observed_value_decimals: u8,
+    pub resolved_at: i64,
+}
+
+#[event]
+pub struct SharesTraded {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: BetSide,
+    pub shares: u64,
+    pub collateral: u64,
+    pub is_buy: bool,
+    pub price_yes_bps: u16,
+}
+
+#[event]
+pub struct LiquidityChanged {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub collateral: u64,
+    pub lp_shares: u64,
+    pub is_deposit: bool,
+}
+
+#[event]
+pub struct OrderPlaced {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub client_order_id: u64,
+    pub side: OrderSide,
+    pub price_bps: u16,
+    pub quantity: u64,
+    pub filled: u64,
+}
+
+#[event]
+pub struct PositionExited {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub penalty: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct MarketExtended {
+    pub market: Pubkey,
+    pub extended_by: Pubkey,
+    pub previous_closing_time: i64,
+    pub new_closing_time: i64,
+}
+
+#[event]
+pub struct MarketClosedEarly {
+    pub market: Pubkey,
+    pub resolver: Pubkey,
+    pub original_closing_time: i64,
+    pub closed_at: i64,
+}
+
+#[event]
+pub struct BetPlaced {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub sequence: u64,
+    pub side: BetSide,
+    // Amount paid, including the platform fee
+    pub amount: u64,
+    pub fee: u64,
+    pub stake: u64,
+    pub weighted_stake: u64,
+    pub yes_pool: u64,
+    pub no_pool: u64,
+    pub payout_multiplier_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market: Pubkey,
+    pub resolver: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct UnclaimedFundsSwept {
+    pub market: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub unclaimed_positions: u64,
+    pub claim_deadline: i64,
+    pub swept_at: i64,
+}
+
 // Error codes
 #[error_code]
 pub enum ErrorCode {
@@ -587,4 +4761,132 @@ pub enum ErrorCode {
     AlreadyClaimed,
     #[msg("No winnings to claim")]
     NoWinningsToClaim,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Resolver is not on the allowlist")]
+    ResolverNotAllowed,
+    #[msg("Resolver is already on the allowlist")]
+    ResolverAlreadyAllowed,
+    #[msg("Resolver allowlist is full")]
+    TooManyResolvers,
+    #[msg("Resolver not found on the allowlist")]
+    ResolverNotFound,
+    #[msg("Resolution rules URI is too long")]
+    RulesUriTooLong,
+    #[msg("Evidence source is too long")]
+    EvidenceSourceTooLong,
+    #[msg("Freeze window must not be negative")]
+    InvalidFreezeWindow,
+    #[msg("Betting is frozen ahead of market close")]
+    BettingFrozen,
+    #[msg("New closing time must be later than the current one")]
+    ClosingTimeNotLater,
+    #[msg("Exit amount must be greater than zero")]
+    InvalidExitAmount,
+    #[msg("Position is smaller than the requested amount")]
+    InsufficientPosition,
+    #[msg("Instruction does not match the market's pricing mode")]
+    WrongPricingMode,
+    #[msg("Market already has bets")]
+    MarketHasBets,
+    #[msg("Liquidity parameter must be greater than zero")]
+    InvalidLiquidity,
+    #[msg("Share amount must be greater than zero")]
+    InvalidShareAmount,
+    #[msg("Price moved beyond the allowed slippage")]
+    SlippageExceeded,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Nothing to withdraw")]
+    NothingToWithdraw,
+    #[msg("Fee is above the allowed maximum")]
+    InvalidFee,
+    #[msg("Pool has no liquidity")]
+    NoLiquidity,
+    #[msg("Outcome mints have already been created")]
+    OutcomeMintsAlreadyCreated,
+    #[msg("Outcome mint does not belong to this market")]
+    OutcomeMintMismatch,
+    #[msg("Outcome mints have not been created for this market")]
+    OutcomeMintsNotCreated,
+    #[msg("Order price must be between 1 and 9999 basis points")]
+    InvalidPrice,
+    #[msg("Order quantity must be a multiple of the lot size and at least the minimum order")]
+    InvalidOrderQuantity,
+    #[msg("Order book side is full")]
+    OrderBookFull,
+    #[msg("Event queue is full, crank consume_events")]
+    EventQueueFull,
+    #[msg("Order not found")]
+    OrderNotFound,
+    #[msg("Order book account does not belong to this market")]
+    OrderBookMismatch,
+    #[msg("Odds moved: payout multiplier after this bet is below the requested minimum")]
+    PayoutBelowMinimum,
+    #[msg("Time-weight bonus is above the allowed maximum")]
+    InvalidTimeWeight,
+    #[msg("Seed split must be at most 10000 basis points")]
+    InvalidSeedSplit,
+    #[msg("Seeding a market requires the creator position and token accounts")]
+    MissingSeedAccounts,
+    #[msg("Virtual liquidity would exceed the treasury subsidy cap")]
+    SubsidyCapExceeded,
+    #[msg("Treasury subsidy must be settled before claiming")]
+    SubsidyNotSettled,
+    #[msg("Minimum bet must not exceed maximum bet")]
+    InvalidBetLimits,
+    #[msg("Bet is below the market's minimum")]
+    BetBelowMinimum,
+    #[msg("Bet is above the market's maximum")]
+    BetAboveMaximum,
+    #[msg("Pool share cap must be at most 10000 basis points")]
+    InvalidPoolShare,
+    #[msg("Bet would exceed the market's per-user position limit")]
+    PositionLimitExceeded,
+    #[msg("Bet would exceed the market's per-user share of the pool")]
+    PoolShareExceeded,
+    #[msg("User does not meet the market's holder requirement")]
+    HolderGateNotMet,
+    #[msg("Wallet is not on the market's allowlist")]
+    NotOnAllowlist,
+    #[msg("Creator is not on the creator allowlist")]
+    CreatorNotAllowed,
+    #[msg("Position still has winnings or refunds to claim")]
+    PositionNotSettled,
+    #[msg("Bet account does not belong to this user and market")]
+    BetAccountMismatch,
+    #[msg("Market still has open positions, liquidity or outcome tokens")]
+    MarketHasOpenPositions,
+    #[msg("Creator bond must be released first")]
+    BondNotReleased,
+    #[msg("Claim window cannot be negative")]
+    InvalidClaimWindow,
+    #[msg("Claim window has expired")]
+    ClaimWindowExpired,
+    #[msg("Claim window is still open")]
+    ClaimWindowOpen,
+    #[msg("Bet account must be passed only on markets that record bets as accounts")]
+    BetRecordModeMismatch,
+    #[msg("Question too long")]
+    QuestionTooLong,
+    #[msg("Description too long")]
+    DescriptionTooLong,
+    #[msg("Token symbol or name too long")]
+    TokenNameTooLong,
+    #[msg("Metadata URI too long")]
+    MetadataUriTooLong,
+    #[msg("Metadata URI and hash must be set together")]
+    InvalidMetadata,
+    #[msg("The same maker OpenOrders account was passed more than once")]
+    DuplicateMakerAccount,
+    #[msg("The OpenOrders account of the next event's maker was not passed")]
+    MakerAccountMissing,
+    #[msg("Creator position can only be passed when seeding the market")]
+    UnexpectedSeedPosition,
+    #[msg("Withdrawal would leave committed subsidies unfunded")]
+    TreasuryReserved,
+    #[msg("LMSR liquidity must be withdrawn first")]
+    LmsrLiquidityNotWithdrawn,
+    #[msg("Vault holds more than payout rounding can leave behind")]
+    DustAboveRoundingBound,
 }
\ No newline at end of file
@@ -0,0 +1,175 @@
+// Fixed-point math for the logarithmic market scoring rule (LMSR)
+//
+// Share quantities and the liquidity parameter `b` are in collateral base units
+// (one winning share redeems one base unit). Intermediate values are u128 fixed
+// point with SCALE = 1e12.
+//
+// Cost function: C(q_yes, q_no) = b * ln(e^(q_yes / b) + e^(q_no / b)),
+// evaluated as max(q) + b * ln(1 + e^(-|q_yes - q_no| / b)) to stay in range.
+
+pub const SCALE: u128 = 1_000_000_000_000;
+
+// ln(2) * SCALE
+pub const LN_2: u128 = 693_147_180_560;
+
+// e^(-x) for fixed-point x >= 0
+pub fn exp_neg(x: u128) -> u128 {
+    // Range reduction: x = k * ln(2) + r with r in [0, ln(2))
+    let k = x / LN_2;
+    if k >= 64 {
+        return 0;
+    }
+    let r = x - k * LN_2;
+
+    // Taylor series for e^(-r); terms shrink quickly since r < 0.7
+    let mut term = SCALE;
+    let mut positive = SCALE;
+    let mut negative = 0u128;
+    let mut n = 1u128;
+    while term > 0 {
+        term = term * r / SCALE / n;
+        if n % 2 == 1 {
+            negative += term;
+        } else {
+            positive += term;
+        }
+        n += 1;
+    }
+
+    (positive - negative) >> k
+}
+
+// ln(1 + y) for fixed-point y in [0, 1]
+pub fn ln_1p(y: u128) -> u128 {
+    // ln(1 + y) = 2 * atanh(z) with z = y / (2 + y) <= 1/3
+    let z = y * SCALE / (2 * SCALE + y);
+    let z_squared = z * z / SCALE;
+
+    let mut power = z;
+    let mut sum = 0u128;
+    let mut n = 1u128;
+    while power > 0 {
+        sum += power / n;
+        power = power * z_squared / SCALE;
+        n += 2;
+    }
+
+    2 * sum
+}
+
+// Cost function C(q_yes, q_no) in SCALE fixed point
+pub fn cost(q_yes: u64, q_no: u64, liquidity: u64) -> u128 {
+    let (high, low) = if q_yes >= q_no { (q_yes, q_no) } else { (q_no, q_yes) };
+    let x = (high - low) as u128 * SCALE / liquidity as u128;
+
+    high as u128 * SCALE + liquidity as u128 * ln_1p(exp_neg(x))
+}
+
+// Collateral needed to buy `shares` of one side, rounded up
+pub fn buy_cost(q_side: u64, q_other: u64, liquidity: u64, shares: u64) -> Option<u64> {
+    let after = cost(q_side.checked_add(shares)?, q_other, liquidity);
+    let before = cost(q_side, q_other, liquidity);
+    let delta = after.checked_sub(before)?;
+
+    u64::try_from((delta + SCALE - 1) / SCALE).ok()
+}
+
+// Collateral returned for selling `shares` of one side, rounded down
+pub fn sell_proceeds(q_side: u64, q_other: u64, liquidity: u64, shares: u64) -> Option<u64> {
+    let before = cost(q_side, q_other, liquidity);
+    let after = cost(q_side.checked_sub(shares)?, q_other, liquidity);
+    let delta = before.checked_sub(after)?;
+
+    u64::try_from(delta / SCALE).ok()
+}
+
+// Worst-case market maker loss, b * ln(2), rounded up with one unit of headroom
+// for fixed-point truncation
+pub fn max_loss(liquidity: u64) -> u64 {
+    ((liquidity as u128 * LN_2 + SCALE - 1) / SCALE) as u64 + 1
+}
+
+// Instantaneous YES price in basis points
+pub fn price_yes_bps(q_yes: u64, q_no: u64, liquidity: u64) -> u16 {
+    let (high, low) = if q_yes >= q_no { (q_yes, q_no) } else { (q_no, q_yes) };
+    let x = (high - low) as u128 * SCALE / liquidity as u128;
+
+    // Price of the leading side: 1 / (1 + e^(-x))
+    let leading = (SCALE * 10000 / (SCALE + exp_neg(x))) as u16;
+    if q_yes >= q_no {
+        leading
+    } else {
+        10000 - leading
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const B: u64 = 1_000_000_000; // 1,000 tokens at 6 decimals
+
+    fn to_f64(fixed: u128) -> f64 {
+        fixed as f64 / SCALE as f64
+    }
+
+    #[test]
+    fn exp_and_ln_match_f64() {
+        for x in [0, SCALE / 1000, SCALE / 2, SCALE, 5 * SCALE, 20 * SCALE] {
+            let expected = (-to_f64(x)).exp();
+            assert!((to_f64(exp_neg(x)) - expected).abs() < 1e-9, "exp_neg({})", x);
+        }
+        for y in [0, SCALE / 1000, SCALE / 2, SCALE] {
+            let expected = to_f64(y).ln_1p();
+            assert!((to_f64(ln_1p(y)) - expected).abs() < 1e-9, "ln_1p({})", y);
+        }
+    }
+
+    #[test]
+    fn cost_at_zero_is_b_ln_2() {
+        for liquidity in [1, 1_000, B, u64::MAX / 4] {
+            let expected = liquidity as u128 * LN_2;
+            let error = cost(0, 0, liquidity).abs_diff(expected);
+            // A few fixed-point units per unit of b from the truncated series
+            assert!(error <= liquidity as u128 * 10, "b = {}", liquidity);
+            assert!(max_loss(liquidity) as u128 * SCALE >= cost(0, 0, liquidity));
+        }
+    }
+
+    #[test]
+    fn prices_sum_to_one() {
+        assert_eq!(price_yes_bps(0, 0, B), 5000);
+        for (q_yes, q_no) in [(0, B), (B / 3, 2 * B), (7 * B, B / 2), (u64::MAX, 0)] {
+            let price_no = price_yes_bps(q_no, q_yes, B);
+            assert_eq!(price_yes_bps(q_yes, q_no, B) + price_no, 10000);
+        }
+        assert!(price_yes_bps(2 * B, 0, B) > 5000);
+    }
+
+    #[test]
+    fn buy_then_sell_does_not_profit() {
+        for (q_yes, q_no) in [(0, 0), (B, 0), (0, 3 * B), (5 * B, 5 * B)] {
+            for shares in [1, 999, B / 7, 4 * B] {
+                let paid = buy_cost(q_yes, q_no, B, shares).unwrap();
+                let returned = sell_proceeds(q_yes + shares, q_no, B, shares).unwrap();
+                assert!(returned <= paid, "q = ({}, {}), shares = {}", q_yes, q_no, shares);
+                // A share never costs more than the unit it redeems for
+                assert!(paid <= shares + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_ratios_do_not_overflow() {
+        let large = u64::MAX / 2;
+        assert_eq!(price_yes_bps(large, 0, 1), 10000);
+        assert_eq!(price_yes_bps(0, large, 1), 0);
+        assert!(cost(large, 0, 1) >= large as u128 * SCALE);
+        assert!(cost(large, large, u64::MAX) > large as u128 * SCALE);
+        assert_eq!(price_yes_bps(large, large, u64::MAX), 5000);
+        assert!(buy_cost(large, 0, 1, large).is_some());
+        assert!(sell_proceeds(large, 0, 1, large).is_some());
+        assert_eq!(buy_cost(u64::MAX, 0, B, 1), None);
+        assert_eq!(sell_proceeds(0, 0, B, 1), None);
+    }
+}
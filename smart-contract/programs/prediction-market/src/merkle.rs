@@ -0,0 +1,85 @@
+// Merkle allowlists for private markets
+//
+// Leaves are keccak256(0x00 || wallet) and interior nodes are
+// keccak256(0x01 || a || b) with their two children in sorted order, so proofs
+// don't need to record left/right positions. The prefixes keep an interior node
+// from being passed off as a leaf.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+
+const LEAF_PREFIX: &[u8] = &[0x00];
+const NODE_PREFIX: &[u8] = &[0x01];
+
+// Whether `wallet` is in the tree with root `root`
+pub fn verify(root: &[u8; 32], wallet: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    let mut node = keccak::hashv(&[LEAF_PREFIX, wallet.as_ref()]).to_bytes();
+    for sibling in proof {
+        node = if node <= *sibling {
+            keccak::hashv(&[NODE_PREFIX, &node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[NODE_PREFIX, sibling, &node]).to_bytes()
+        };
+    }
+    node == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(wallet: &Pubkey) -> [u8; 32] {
+        keccak::hashv(&[LEAF_PREFIX, wallet.as_ref()]).to_bytes()
+    }
+
+    fn node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        let (low, high) = if a <= b { (a, b) } else { (b, a) };
+        keccak::hashv(&[NODE_PREFIX, low, high]).to_bytes()
+    }
+
+    // Four-wallet tree: root = node(node(l0, l1), node(l2, l3))
+    fn tree() -> ([Pubkey; 4], [[u8; 32]; 2], [u8; 32]) {
+        let wallets = [(); 4].map(|_| Pubkey::new_unique());
+        let leaves = wallets.map(|wallet| leaf(&wallet));
+        let left = node(&leaves[0], &leaves[1]);
+        let right = node(&leaves[2], &leaves[3]);
+        (wallets, [left, right], node(&left, &right))
+    }
+
+    #[test]
+    fn valid_proofs_verify() {
+        let (wallets, [left, right], root) = tree();
+        assert!(verify(&root, &wallets[0], &[leaf(&wallets[1]), right]));
+        assert!(verify(&root, &wallets[3], &[leaf(&wallets[2]), left]));
+    }
+
+    #[test]
+    fn wrong_leaf_is_rejected() {
+        let (wallets, [_, right], root) = tree();
+        assert!(!verify(&root, &Pubkey::new_unique(), &[leaf(&wallets[1]), right]));
+        assert!(!verify(&root, &wallets[2], &[leaf(&wallets[1]), right]));
+    }
+
+    #[test]
+    fn truncated_proof_is_rejected() {
+        let (wallets, _, root) = tree();
+        assert!(!verify(&root, &wallets[0], &[leaf(&wallets[1])]));
+        assert!(!verify(&root, &wallets[0], &[]));
+    }
+
+    #[test]
+    fn interior_node_is_not_a_leaf() {
+        // Second preimage: an interior node plus the rest of its proof does reach the
+        // root, so the node must not be accepted in place of a leaf
+        let (wallets, [left, right], root) = tree();
+        assert_eq!(node(&left, &right), root);
+        assert!(!verify(&root, &Pubkey::new_from_array(left), &[right]));
+
+        // Leaf and node hashes of the same bytes differ thanks to the prefixes
+        let (l0, l1) = (leaf(&wallets[0]), leaf(&wallets[1]));
+        let mut children = l0.to_vec();
+        children.extend_from_slice(&l1);
+        assert_ne!(keccak::hashv(&[LEAF_PREFIX, &children]).to_bytes(), node(&l0, &l1));
+        assert_ne!(keccak::hashv(&[LEAF_PREFIX, &l0]).to_bytes(), keccak::hashv(&[NODE_PREFIX, &l0]).to_bytes());
+    }
+}
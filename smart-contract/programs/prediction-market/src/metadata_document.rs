@@ -0,0 +1,127 @@
+// Off-chain market metadata documents
+//
+// A market can point at a JSON document holding its question, rules, data
+// sources and edge cases (the same fields as the Supabase `markets` table)
+// instead of storing long text on chain. MarketMetadata keeps the document's
+// URI and the market keeps the SHA-256 hash of its exact bytes, so clients can
+// detect tampering.
+
+use anchor_lang::solana_program::hash;
+
+// JSON Schema (draft-07) that documents must follow
+pub const MARKET_METADATA_SCHEMA: &str = include_str!("../market-metadata.schema.json");
+
+// Schema version written to the document's `version` field
+pub const MARKET_METADATA_VERSION: u8 = 1;
+
+// SHA-256 of a metadata document, as stored on the market
+pub fn content_hash(document: &[u8]) -> [u8; 32] {
+    hash::hash(document).to_bytes()
+}
+
+// Typed form of the schema for Rust clients (enable the `serde` feature)
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MarketMetadataDocument {
+    pub version: u8,
+    pub question: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub question_type: Option<QuestionType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closing_time: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<TokenDocument>,
+    pub resolution_criteria: String,
+    pub data_sources: String,
+    pub edge_cases: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispute_resolution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub historical_context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquidity_context: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuestionType {
+    YesNo,
+    MultipleChoice,
+    Numeric,
+    Date,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TokenDocument {
+    pub mint: String,
+    pub symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl MarketMetadataDocument {
+    // Checks the schema can't express through types alone
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.version != MARKET_METADATA_VERSION {
+            return Err("unsupported version");
+        }
+        if self.question.is_empty() {
+            return Err("question is empty");
+        }
+        if self.resolution_criteria.is_empty() || self.data_sources.is_empty() {
+            return Err("resolution criteria and data sources are required");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Market;
+
+    const DOCUMENT: &[u8] = br#"{"version":1,"question":"Will WIF hit $10 by end of 2024?","question_type":"yes-no","resolution_criteria":"YES if WIF trades at or above $10 on any major exchange before the closing time.","data_sources":"CoinGecko, Binance","edge_cases":"Wicks under one minute are ignored."}"#;
+
+    // sha256 of DOCUMENT, computed independently
+    const DOCUMENT_HASH: [u8; 32] = [
+        0x38, 0x87, 0xcf, 0x77, 0x27, 0xed, 0xf7, 0x7c, 0x7c, 0x49, 0x79, 0xb7, 0x53, 0x95, 0xf5, 0xd4,
+        0xa3, 0x28, 0x09, 0xf1, 0x9e, 0x50, 0xd5, 0xf5, 0x6d, 0x5f, 0x46, 0xab, 0x01, 0xfd, 0xd6, 0xdf,
+    ];
+
+    #[test]
+    fn content_hash_is_sha256() {
+        assert_eq!(content_hash(DOCUMENT), DOCUMENT_HASH);
+    }
+
+    #[test]
+    fn metadata_matches_committed_document() {
+        let mut market: Market = bytemuck::Zeroable::zeroed();
+        assert!(!market.metadata_matches(DOCUMENT));
+
+        market.metadata_hash = DOCUMENT_HASH;
+        assert!(market.metadata_matches(DOCUMENT));
+        assert!(!market.metadata_matches(&DOCUMENT[1..]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn question_type_is_kebab_case() {
+        let document: MarketMetadataDocument = serde_json::from_slice(DOCUMENT).unwrap();
+        assert_eq!(document.question_type, Some(QuestionType::YesNo));
+        assert_eq!(document.validate(), Ok(()));
+
+        let unknown = String::from_utf8_lossy(DOCUMENT).replace("yes-no", "yes_no");
+        assert!(serde_json::from_str::<MarketMetadataDocument>(&unknown).is_err());
+    }
+}
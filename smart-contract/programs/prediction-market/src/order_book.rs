@@ -0,0 +1,240 @@
+// Zero-copy central limit order book for YES outcome tokens
+//
+// Bids and asks are priced in basis points of one collateral token per YES
+// token. Each side is a fixed-capacity slab kept sorted best-first (price, then
+// time), so the top of book is always index 0. Fills against resting orders are
+// written to the event queue and credited to the maker's OpenOrders by the crank.
+// A full side evicts its worst order for a better-priced one; the eviction is
+// queued as an out event so the crank can unlock the evicted maker's funds.
+
+use anchor_lang::prelude::*;
+
+// Resting orders per side
+pub const MAX_ORDERS_PER_SIDE: usize = 64;
+
+// Fill events buffered until cranked
+pub const EVENT_QUEUE_CAPACITY: usize = 100;
+
+// Order quantities are whole lots, so quantity * price_bps / 10000 is exact
+pub const ORDER_LOT_SIZE: u64 = 10_000;
+
+// Smallest order (100 lots), so filling a side with orders isn't free
+pub const MIN_ORDER_QUANTITY: u64 = 100 * ORDER_LOT_SIZE;
+
+// Maker orders matched per place_order, to bound compute
+pub const MAX_MATCHES_PER_ORDER: usize = 10;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct Order {
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub client_order_id: u64,
+    pub quantity: u64,
+    pub price_bps: u16,
+    pub _padding: [u8; 6],
+}
+
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub market: Pubkey,
+    pub next_order_id: u64,
+    pub bid_count: u32,
+    pub ask_count: u32,
+    pub bids: [Order; MAX_ORDERS_PER_SIDE],
+    pub asks: [Order; MAX_ORDERS_PER_SIDE],
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+impl OrderBook {
+    pub const INIT_SPACE: usize = 32 + 8 + 4 + 4 + 2 * MAX_ORDERS_PER_SIDE * 64 + 1 + 7;
+
+    fn slab_mut(&mut self, side: OrderSide) -> (&mut [Order; MAX_ORDERS_PER_SIDE], &mut u32) {
+        match side {
+            OrderSide::Bid => (&mut self.bids, &mut self.bid_count),
+            OrderSide::Ask => (&mut self.asks, &mut self.ask_count),
+        }
+    }
+
+    // Best resting order on a side
+    pub fn best(&self, side: OrderSide) -> Option<&Order> {
+        let (orders, count) = match side {
+            OrderSide::Bid => (&self.bids, self.bid_count),
+            OrderSide::Ask => (&self.asks, self.ask_count),
+        };
+        if count == 0 {
+            None
+        } else {
+            Some(&orders[0])
+        }
+    }
+
+    // Reduce the best order on a side by `quantity`, removing it once empty
+    pub fn fill_best(&mut self, side: OrderSide, quantity: u64) {
+        let (orders, count) = self.slab_mut(side);
+        orders[0].quantity -= quantity;
+        if orders[0].quantity == 0 {
+            remove_at(orders, count, 0);
+        }
+    }
+
+    // Insert a resting order behind any order at the same or a better price.
+    // On a full side, the worst order is evicted and returned if the new one beats it.
+    pub fn insert(&mut self, side: OrderSide, order: Order) -> Result<Option<Order>> {
+        let (orders, count) = self.slab_mut(side);
+        let mut evicted = None;
+        if *count as usize == MAX_ORDERS_PER_SIDE {
+            let worst = orders[MAX_ORDERS_PER_SIDE - 1];
+            let better = match side {
+                OrderSide::Bid => order.price_bps > worst.price_bps,
+                OrderSide::Ask => order.price_bps < worst.price_bps,
+            };
+            require!(better, crate::ErrorCode::OrderBookFull);
+            remove_at(orders, count, MAX_ORDERS_PER_SIDE - 1);
+            evicted = Some(worst);
+        }
+        let len = *count as usize;
+
+        let index = orders[..len]
+            .iter()
+            .position(|resting| match side {
+                OrderSide::Bid => order.price_bps > resting.price_bps,
+                OrderSide::Ask => order.price_bps < resting.price_bps,
+            })
+            .unwrap_or(len);
+
+        orders.copy_within(index..len, index + 1);
+        orders[index] = order;
+        *count += 1;
+        Ok(evicted)
+    }
+
+    // Remove an order by id, returning it
+    pub fn remove(&mut self, side: OrderSide, order_id: u64) -> Option<Order> {
+        let (orders, count) = self.slab_mut(side);
+        let len = *count as usize;
+        let index = orders[..len].iter().position(|order| order.order_id == order_id)?;
+        let order = orders[index];
+        remove_at(orders, count, index);
+        Some(order)
+    }
+}
+
+fn remove_at(orders: &mut [Order; MAX_ORDERS_PER_SIDE], count: &mut u32, index: usize) {
+    let len = *count as usize;
+    orders.copy_within(index + 1..len, index);
+    orders[len - 1] = Order::default();
+    *count -= 1;
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct FillEvent {
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub order_id: u64,
+    pub quantity: u64,
+    pub price_bps: u16,
+    // 0 = maker was a bid, 1 = maker was an ask
+    pub maker_side: u8,
+    // 0 = fill, 1 = out (the maker's order was evicted unfilled; taker is unset)
+    pub kind: u8,
+    pub _padding: [u8; 4],
+}
+
+impl FillEvent {
+    pub fn is_out(&self) -> bool {
+        self.kind == 1
+    }
+
+    pub fn maker_side(&self) -> OrderSide {
+        if self.maker_side == 0 {
+            OrderSide::Bid
+        } else {
+            OrderSide::Ask
+        }
+    }
+}
+
+#[account(zero_copy)]
+pub struct EventQueue {
+    pub market: Pubkey,
+    // Total events ever pushed, for indexers
+    pub seq_num: u64,
+    pub head: u32,
+    pub count: u32,
+    pub events: [FillEvent; EVENT_QUEUE_CAPACITY],
+}
+
+impl EventQueue {
+    pub const INIT_SPACE: usize = 32 + 8 + 4 + 4 + EVENT_QUEUE_CAPACITY * 88;
+
+    pub fn push(&mut self, event: FillEvent) -> Result<()> {
+        let len = self.count as usize;
+        require!(len < EVENT_QUEUE_CAPACITY, crate::ErrorCode::EventQueueFull);
+
+        let index = (self.head as usize + len) % EVENT_QUEUE_CAPACITY;
+        self.events[index] = event;
+        self.count += 1;
+        self.seq_num += 1;
+        Ok(())
+    }
+
+    pub fn peek(&self) -> Option<&FillEvent> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(&self.events[self.head as usize])
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.events[self.head as usize] = FillEvent::default();
+        self.head = ((self.head as usize + 1) % EVENT_QUEUE_CAPACITY) as u32;
+        self.count -= 1;
+    }
+}
+
+// Collateral value of `quantity` YES tokens at `price_bps`
+pub fn quote_amount(quantity: u64, price_bps: u16) -> u64 {
+    (quantity as u128 * price_bps as u128 / 10000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(order_id: u64, price_bps: u16) -> Order {
+        Order {
+            owner: Pubkey::new_unique(),
+            order_id,
+            client_order_id: 0,
+            quantity: MIN_ORDER_QUANTITY,
+            price_bps,
+            _padding: [0; 6],
+        }
+    }
+
+    #[test]
+    fn full_side_evicts_its_worst_order() {
+        let mut book: OrderBook = bytemuck::Zeroable::zeroed();
+        for order_id in 0..MAX_ORDERS_PER_SIDE as u64 {
+            assert!(book.insert(OrderSide::Bid, bid(order_id, 1)).unwrap().is_none());
+        }
+
+        // Matching the worst price isn't enough to take its place
+        assert!(book.insert(OrderSide::Bid, bid(100, 1)).is_err());
+
+        let evicted = book.insert(OrderSide::Bid, bid(101, 4000)).unwrap().unwrap();
+        assert_eq!(evicted.order_id, MAX_ORDERS_PER_SIDE as u64 - 1);
+        assert_eq!(book.bid_count as usize, MAX_ORDERS_PER_SIDE);
+        assert_eq!(book.best(OrderSide::Bid).unwrap().order_id, 101);
+    }
+}
@@ -0,0 +1,81 @@
+#!/usr/bin/env bash
+# Compare placeBet compute units between a baseline revision and the working tree.
+#
+# Runs the test suite against a fresh local validator for each tree, reads the compute
+# units of every successful placeBet from the ledger, prints both lists and fails
+# unless the working tree's first placeBet (the one that also creates the position
+# and bet accounts) uses fewer units than the baseline's.
+#
+# Usage: scripts/compare-place-bet-cu.sh <baseline-ref>
+#   e.g. the last commit before the zero-copy market layout
+set -euo pipefail
+
+base_ref=${1:?usage: $0 <baseline-ref>}
+here=$(cd "$(dirname "$0")/.." && pwd)
+repo=$(git -C "$here" rev-parse --show-toplevel)
+subdir=${here#"$repo"}
+
+baseline=$(mktemp -d)
+cleanup() {
+  git -C "$repo" worktree remove --force "$baseline" 2>/dev/null || true
+}
+trap cleanup EXIT
+git -C "$repo" worktree add --detach "$baseline" "$base_ref" >/dev/null
+ln -s "$here/node_modules" "$baseline$subdir/node_modules"
+
+# Compute units of each successful placeBet by program $1 on the local validator, oldest first
+place_bet_units() {
+  (cd "$here" && node - "$1" <<'EOF'
+const { Connection, PublicKey } = require("@solana/web3.js");
+
+(async () => {
+  const connection = new Connection("http://127.0.0.1:8899", "confirmed");
+  const signatures = await connection.getSignaturesForAddress(new PublicKey(process.argv[2]), { limit: 1000 });
+  for (const { signature, err } of signatures.reverse()) {
+    if (err) continue;
+    const tx = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    if (tx.meta.logMessages.includes("Program log: Instruction: PlaceBet")) {
+      console.log(tx.meta.computeUnitsConsumed);
+    }
+  }
+})();
+EOF
+  )
+}
+
+measure() {
+  # ledger and validator aren't local: the exit trap runs after this function returns
+  local tree=$1
+  ledger=$(mktemp -d)
+  solana-test-validator --reset --quiet --ledger "$ledger" >/dev/null 2>&1 &
+  validator=$!
+  trap 'kill "$validator" 2>/dev/null; rm -rf "$ledger"' EXIT
+  until solana cluster-version --url localhost >/dev/null 2>&1; do sleep 1; done
+
+  (cd "$tree" && anchor test --skip-local-validator >&2)
+  place_bet_units "$(solana address -k "$tree/target/deploy/prediction_market-keypair.json")"
+}
+
+echo "Measuring $base_ref..." >&2
+# Each measurement runs in its own subshell, whose exit trap stops its validator
+base_units=$(measure "$baseline$subdir")
+echo "Measuring the working tree..." >&2
+head_units=$(measure "$here")
+
+echo "placeBet compute units, $base_ref:" $base_units
+echo "placeBet compute units, working tree:" $head_units
+
+base_first=$(echo "$base_units" | head -1)
+head_first=$(echo "$head_units" | head -1)
+if [ -z "$base_first" ] || [ -z "$head_first" ]; then
+  echo "No placeBet transactions found" >&2
+  exit 1
+fi
+if [ "$head_first" -ge "$base_first" ]; then
+  echo "First placeBet uses $head_first CU, not fewer than $base_first on $base_ref" >&2
+  exit 1
+fi
+echo "First placeBet: $base_first -> $head_first CU"
@@ -2,8 +2,9 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { PredictionMarket } from "../target/types/prediction_market";
 import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress, createMint, createAccount, mintTo } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress, createMint, createAccount, getOrCreateAssociatedTokenAccount, mintTo } from "@solana/spl-token";
 import { expect } from "chai";
+import { createHash } from "crypto";
 
 describe("prediction-market", () => {
   // Configure the client to use the local cluster.
@@ -12,6 +13,23 @@ describe("prediction-market", () => {
   const program = anchor.workspace.PredictionMarket as Program<PredictionMarket>;
   const provider = anchor.getProvider();
 
+  // Question, description, rules URI and evidence live next to the zero-copy market account
+  const metadataPDA = (market: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("market_metadata"), market.toBuffer()], program.programId)[0];
+
+  // Compute units a confirmed transaction consumed, for benchmarking hot instructions
+  const computeUnits = async (signature: string) => {
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    return tx.meta.computeUnitsConsumed;
+  };
+
+  // Upper bound for a placeBet that creates the position and bet accounts and moves the
+  // stake and fee. Raise it only on purpose: the zero-copy market exists to keep this low.
+  const PLACE_BET_CU_BUDGET = 60_000;
+
   // Test accounts
   let globalPDA: PublicKey;
   let globalBump: number;
@@ -61,7 +79,6 @@ describe("prediction-market", () => {
     user1TokenAccount = await getAssociatedTokenAddress(testTokenMint, user1.publicKey);
     user2TokenAccount = await getAssociatedTokenAddress(testTokenMint, user2.publicKey);
     marketTokenAccount = await getAssociatedTokenAddress(testTokenMint, marketPDA);
-    platformTokenAccount = await getAssociatedTokenAddress(testTokenMint, authority.publicKey);
 
     // Create user token accounts
     await createAccount(provider.connection, user1, testTokenMint, user1.publicKey);
@@ -76,6 +93,11 @@ describe("prediction-market", () => {
       [Buffer.from("global")],
       program.programId
     );
+
+    // Fees are collected in the treasury, the global PDA's token account
+    platformTokenAccount = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, authority, testTokenMint, globalPDA, true)
+    ).address;
   });
 
   it("Initializes the global state", async () => {
@@ -97,10 +119,68 @@ describe("prediction-market", () => {
     expect(globalState.marketCount.toNumber()).to.equal(0);
     expect(globalState.totalVolume.toNumber()).to.equal(0);
     expect(globalState.totalFeesCollected.toNumber()).to.equal(0);
+    expect(globalState.resolvers).to.have.lengthOf(0);
+    expect(globalState.subsidyCap.toNumber()).to.equal(0);
+  });
+
+  it("Adds a resolver to the allowlist", async () => {
+    const tx = await program.methods
+      .addResolver(authority.publicKey)
+      .accounts({
+        global: globalPDA,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    console.log("Add resolver transaction:", tx);
+
+    const globalState = await program.account.global.fetch(globalPDA);
+    expect(globalState.resolvers.map((key) => key.toString())).to.deep.equal([authority.publicKey.toString()]);
+  });
+
+  it("Adds a market creator to the allowlist", async () => {
+    const [creatorEntryPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("creator"), user1.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const tx = await program.methods
+      .addCreator(user1.publicKey)
+      .accounts({
+        creatorEntry: creatorEntryPDA,
+        global: globalPDA,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    console.log("Add creator transaction:", tx);
+
+    const entry = await program.account.creatorEntry.fetch(creatorEntryPDA);
+    expect(entry.creator.toString()).to.equal(user1.publicKey.toString());
+
+    // Creation stays open to everyone until the authority switches modes
+    const globalState = await program.account.global.fetch(globalPDA);
+    expect(globalState.creationMode).to.deep.equal({ permissionless: {} });
   });
 
   it("Creates a new market", async () => {
     const closingTime = Math.floor(Date.now() / 1000) + 86400; // 24 hours from now
+    const rulesHash = Array.from(Buffer.alloc(32, 1)); // SHA-256 of the resolution rules document
+
+    // Off-chain document following market-metadata.schema.json, committed to by its SHA-256
+    const metadataDocument = Buffer.from(JSON.stringify({
+      version: 1,
+      question: "Will WIF hit $10 by end of 2024?",
+      question_type: "yes-no",
+      resolution_criteria: "Resolves YES if the WIF/USD price reaches $10.00 before closing",
+      data_sources: "CoinGecko API",
+      edge_cases: "Exchange outages are ignored if CoinGecko reports a price",
+    }));
+    const metadataUri = "https://polymeme.app/markets/0/metadata.json";
+    const metadataHash = Array.from(createHash("sha256").update(metadataDocument).digest());
 
     [marketPDA, marketBump] = PublicKey.findProgramAddressSync(
       [Buffer.from("market"), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
@@ -114,12 +194,37 @@ describe("prediction-market", () => {
         new anchor.BN(closingTime),
         testTokenMint,
         "WIF",
-        "dogwifhat"
+        "dogwifhat",
+        { key: { resolver: authority.publicKey } },
+        rulesHash,
+        "https://polymeme.app/markets/0/rules.json",
+        new anchor.BN(3600), // Freeze betting 1 hour before close
+        { flat: {} },
+        new anchor.BN(0), // No creator seed
+        0,
+        new anchor.BN(1 * 10**6), // Minimum bet: 1 token
+        new anchor.BN(500 * 10**6), // Maximum bet: 500 tokens
+        new anchor.BN(800 * 10**6), // Per-user position cap: 800 tokens
+        9000, // No user may hold more than 90% of a side once others have bet
+        { open: {} }, // Anyone may bet
+        Array.from(Buffer.alloc(32)), // No allowlist: public market
+        new anchor.BN(30 * 86400), // Winnings claimable for 30 days after resolution
+        { account: {} }, // Keep a Bet account per wager
+        metadataUri,
+        metadataHash
       )
       .accounts({
         market: marketPDA,
+        marketMetadata: metadataPDA(marketPDA),
         global: globalPDA,
         creator: user1.publicKey,
+        creatorEntry: null, // Creation is permissionless
+        creatorPosition: null,
+        creatorTokenAccount: null,
+        marketTokenAccount: null,
+        requiredTokenMintAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .signers([user1])
@@ -131,10 +236,49 @@ describe("prediction-market", () => {
     const market = await program.account.market.fetch(marketPDA);
     expect(market.id.toNumber()).to.equal(0);
     expect(market.creator.toString()).to.equal(user1.publicKey.toString());
-    expect(market.question).to.equal("Will WIF hit $10 by end of 2024?");
+    expect(market.resolverKind).to.equal(0); // Key
+    expect(market.resolverKey.toString()).to.equal(authority.publicKey.toString());
+    expect(market.rulesHash).to.deep.equal(rulesHash);
+    expect(market.freezeWindow.toNumber()).to.equal(3600);
+    expect(market.minBet.toNumber()).to.equal(1 * 10**6);
+    expect(market.maxBet.toNumber()).to.equal(500 * 10**6);
+    expect(market.maxPosition.toNumber()).to.equal(800 * 10**6);
+    expect(market.maxPoolShareBps).to.equal(9000);
+    expect(market.holderGateKind).to.equal(0); // Open
+    expect(market.creatorBond.toNumber()).to.equal(0); // No creation costs configured
+    expect(market.claimWindow.toNumber()).to.equal(30 * 86400);
     expect(market.requiredTokenMint.toString()).to.equal(testTokenMint.toString());
+    expect(Buffer.from(market.requiredTokenSymbol).toString().replace(/\0+$/, "")).to.equal("WIF");
+    expect(market.metadataHash).to.deep.equal(metadataHash);
     expect(market.yesPool.toNumber()).to.equal(0);
     expect(market.noPool.toNumber()).to.equal(0);
+
+    const metadata = await program.account.marketMetadata.fetch(metadataPDA(marketPDA));
+    expect(metadata.market.toString()).to.equal(marketPDA.toString());
+    expect(metadata.question).to.equal("Will WIF hit $10 by end of 2024?");
+    expect(metadata.rulesUri).to.equal("https://polymeme.app/markets/0/rules.json");
+    expect(metadata.metadataUri).to.equal(metadataUri);
+    expect(metadata.evidence).to.be.null;
+  });
+
+  it("Extends the market closing time", async () => {
+    const before = await program.account.market.fetch(marketPDA);
+    const newClosingTime = before.closingTime.add(new anchor.BN(3600));
+
+    const tx = await program.methods
+      .extendMarket(newClosingTime)
+      .accounts({
+        market: marketPDA,
+        global: globalPDA,
+        authority: user1.publicKey, // Market creator
+      })
+      .signers([user1])
+      .rpc();
+
+    console.log("Extend market transaction:", tx);
+
+    const market = await program.account.market.fetch(marketPDA);
+    expect(market.closingTime.toNumber()).to.equal(newClosingTime.toNumber());
   });
 
   it("Places a bet on YES", async () => {
@@ -154,10 +298,12 @@ describe("prediction-market", () => {
     // Create market token account
     await createAccount(provider.connection, user1, testTokenMint, marketPDA);
 
-    const tx = await program.methods
+    const placeYesBet = () => program.methods
       .placeBet(
         new anchor.BN(betAmount),
-        { yes: {} }
+        { yes: {} },
+        new anchor.BN(10000), // Only bettor so far: 1x payout
+        [] // Public market: no allowlist proof
       )
       .accounts({
         bet: betPDA,
@@ -168,19 +314,46 @@ describe("prediction-market", () => {
         userTokenAccount: user1TokenAccount,
         marketTokenAccount: marketTokenAccount,
         platformTokenAccount: platformTokenAccount,
+        gateTokenAccount: null, // Market is not holder-gated
+        gateMetadata: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .signers([user1])
+      .rpc({ commitment: "confirmed" });
+
+    // The market caps pool share at 90%, so an opening bet on an empty side is rejected
+    // until the side is measured against a minimum depth
+    let errorCode: string | undefined;
+    try {
+      await placeYesBet();
+    } catch (err) {
+      errorCode = (err as anchor.AnchorError).error?.errorCode?.code;
+    }
+    expect(errorCode).to.equal("PoolShareExceeded");
+
+    await program.methods
+      .setMinSideDepth(new anchor.BN(200 * 10**6)) // 97.5 of 200 tokens is under the cap
+      .accounts({
+        global: globalPDA,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
       .rpc();
 
+    const tx = await placeYesBet();
+
     console.log("Place bet transaction:", tx);
+    const placeBetUnits = await computeUnits(tx);
+    console.log("place_bet compute units (first bet, creates position):", placeBetUnits);
+    expect(placeBetUnits).to.be.at.most(PLACE_BET_CU_BUDGET);
 
     // Fetch the bet
     const bet = await program.account.bet.fetch(betPDA);
     expect(bet.user.toString()).to.equal(user1.publicKey.toString());
     expect(bet.amount.toNumber()).to.equal(97500000); // 97.5 tokens (after 2.5% fee)
+    expect(bet.weightedAmount.toNumber()).to.equal(97500000); // flat curve: 1x weight
     expect(bet.side).to.deep.equal({ yes: {} });
     expect(bet.claimed).to.be.false;
 
@@ -194,8 +367,10 @@ describe("prediction-market", () => {
     const market = await program.account.market.fetch(marketPDA);
     expect(market.yesPool.toNumber()).to.equal(97500000); // 97.5 tokens
     expect(market.noPool.toNumber()).to.equal(0);
+    expect(market.yesWeightedPool.toNumber()).to.equal(97500000);
     expect(market.yesBets.toNumber()).to.equal(1);
     expect(market.noBets.toNumber()).to.equal(0);
+    expect(market.betSequence.toNumber()).to.equal(1);
 
     // Fetch the global state
     const globalState = await program.account.global.fetch(globalPDA);
@@ -220,7 +395,9 @@ describe("prediction-market", () => {
     const tx = await program.methods
       .placeBet(
         new anchor.BN(betAmount),
-        { no: {} }
+        { no: {} },
+        new anchor.BN(25000), // Expect at least 2.5x (actual: 146.25 / 48.75 = 3x)
+        []
       )
       .accounts({
         bet: betPDA,
@@ -231,14 +408,19 @@ describe("prediction-market", () => {
         userTokenAccount: user2TokenAccount,
         marketTokenAccount: marketTokenAccount,
         platformTokenAccount: platformTokenAccount,
+        gateTokenAccount: null, // Market is not holder-gated
+        gateMetadata: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .signers([user2])
-      .rpc();
+      .rpc({ commitment: "confirmed" });
 
     console.log("Place NO bet transaction:", tx);
+    const placeBetUnits = await computeUnits(tx);
+    console.log("place_bet compute units:", placeBetUnits);
+    expect(placeBetUnits).to.be.at.most(PLACE_BET_CU_BUDGET);
 
     // Fetch the market
     const market = await program.account.market.fetch(marketPDA);
@@ -248,22 +430,61 @@ describe("prediction-market", () => {
     expect(market.noBets.toNumber()).to.equal(1);
   });
 
+  it("Exits part of a position before close", async () => {
+    const exitAmount = 10 * 10**6; // 10 tokens
+
+    const tx = await program.methods
+      .exitPosition(new anchor.BN(exitAmount), { no: {} })
+      .accounts({
+        market: marketPDA,
+        position: positionPDA,
+        user: user2.publicKey,
+        userTokenAccount: user2TokenAccount,
+        marketTokenAccount: marketTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([user2])
+      .rpc();
+
+    console.log("Exit position transaction:", tx);
+
+    const position = await program.account.position.fetch(positionPDA);
+    expect(position.noAmount.toNumber()).to.equal(38750000); // 48.75 - 10 tokens
+
+    // 10% penalty stays in the pool for the remaining bettors
+    const market = await program.account.market.fetch(marketPDA);
+    expect(market.noPool.toNumber()).to.equal(38750000);
+    expect(market.noBets.toNumber()).to.equal(1);
+    expect(market.exitPenaltyPool.toNumber()).to.equal(1000000); // 1 token
+  });
+
   it("Resolves the market", async () => {
+    const evidenceHash = Array.from(Buffer.alloc(32, 2)); // SHA-256 of the evidence snapshot
+
     const tx = await program.methods
-      .resolveMarket({ yes: {} })
+      .resolveMarket({ yes: {} }, evidenceHash, "coingecko:dogwifhat", new anchor.BN(1023), 2)
       .accounts({
         market: marketPDA,
-        resolver: user1.publicKey, // Market creator
+        marketMetadata: metadataPDA(marketPDA),
+        global: globalPDA,
+        resolver: authority.publicKey, // Designated resolver, not the creator
       })
-      .signers([user1])
+      .signers([authority])
       .rpc();
 
     console.log("Resolve market transaction:", tx);
 
     // Fetch the market
     const market = await program.account.market.fetch(marketPDA);
-    expect(market.status).to.deep.equal({ resolved: {} });
-    expect(market.result).to.deep.equal({ yes: {} });
+    expect(market.status).to.equal(1); // Resolved
+    expect(market.result).to.equal(1); // Yes
+
+    const { evidence } = await program.account.marketMetadata.fetch(metadataPDA(marketPDA));
+    expect(evidence.hash).to.deep.equal(evidenceHash);
+    expect(evidence.source).to.equal("coingecko:dogwifhat");
+    expect(evidence.observedValue.toNumber()).to.equal(1023); // $10.23
+    expect(evidence.observedValueDecimals).to.equal(2);
   });
 
   it("Claims winnings", async () => {
@@ -291,8 +512,659 @@ describe("prediction-market", () => {
 
     console.log("Claim winnings transaction:", tx);
 
-    // Fetch the bet to verify it's claimed
-    const bet = await program.account.bet.fetch(betPDA);
-    expect(bet.claimed).to.be.true;
+    // The claimed bet is closed and its rent returned
+    const bet = await program.account.bet.fetchNullable(betPDA);
+    expect(bet).to.be.null;
+  });
+
+  it("Closes a losing position", async () => {
+    const [losingPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), marketPDA.toBuffer(), user2.publicKey.toBuffer()],
+      program.programId
+    );
+    const openBefore = (await program.account.market.fetch(marketPDA)).openPositions.toNumber();
+
+    const tx = await program.methods
+      .closePosition()
+      .accounts({
+        market: marketPDA,
+        position: losingPositionPDA,
+        user: user2.publicKey,
+      })
+      .signers([user2])
+      .rpc();
+
+    console.log("Close position transaction:", tx);
+
+    const position = await program.account.position.fetchNullable(losingPositionPDA);
+    expect(position).to.be.null;
+
+    // Closing decrements the count; only the winner's position is left before finalizing
+    const market = await program.account.market.fetch(marketPDA);
+    expect(market.openPositions.toNumber()).to.equal(openBefore - 1);
+    expect(market.openPositions.toNumber()).to.equal(1);
+  });
+
+  it("Creates an LMSR market and buys shares", async () => {
+    const closingTime = Math.floor(Date.now() / 1000) + 86400;
+    const liquidity = 100 * 10**6; // b = 100 tokens
+
+    const [lmsrMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(1).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const lmsrMarketTokenAccount = await getAssociatedTokenAddress(testTokenMint, lmsrMarketPDA, true);
+
+    await program.methods
+      .createMarket(
+        "Will WIF flip BONK by end of 2024?",
+        "LMSR test market",
+        new anchor.BN(closingTime),
+        testTokenMint,
+        "WIF",
+        "dogwifhat",
+        { key: { resolver: authority.publicKey } },
+        Array.from(Buffer.alloc(32, 1)),
+        "https://polymeme.app/markets/1/rules.json",
+        new anchor.BN(0),
+        { flat: {} },
+        new anchor.BN(0), // No creator seed
+        0,
+        new anchor.BN(0), // Global default bet limits
+        new anchor.BN(0),
+        new anchor.BN(0), // No exposure caps
+        0,
+        { open: {} },
+        Array.from(Buffer.alloc(32)),
+        new anchor.BN(0), // Claims never expire
+        { account: {} },
+        "", // No off-chain metadata document
+        Array.from(Buffer.alloc(32))
+      )
+      .accounts({
+        market: lmsrMarketPDA,
+        marketMetadata: metadataPDA(lmsrMarketPDA),
+        global: globalPDA,
+        creator: user1.publicKey,
+        creatorEntry: null, // Creation is permissionless
+        creatorPosition: null,
+        creatorTokenAccount: null,
+        marketTokenAccount: null,
+        requiredTokenMintAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    await createAccount(provider.connection, user1, testTokenMint, lmsrMarketPDA);
+
+    await program.methods
+      .initializeLmsr(new anchor.BN(liquidity))
+      .accounts({
+        market: lmsrMarketPDA,
+        creator: user1.publicKey,
+        creatorTokenAccount: user1TokenAccount,
+        marketTokenAccount: lmsrMarketTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+
+    let market = await program.account.market.fetch(lmsrMarketPDA);
+    expect(market.pricingMode).to.equal(1); // Lmsr
+    expect(market.lmsr.funding.toNumber()).to.equal(69314720); // ceil(b * ln 2) + 1
+
+    const [lmsrPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), lmsrMarketPDA.toBuffer(), user2.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const tx = await program.methods
+      .buyShares({ yes: {} }, new anchor.BN(50 * 10**6), new anchor.BN(30 * 10**6))
+      .accounts({
+        position: lmsrPositionPDA,
+        market: lmsrMarketPDA,
+        global: globalPDA,
+        user: user2.publicKey,
+        userTokenAccount: user2TokenAccount,
+        marketTokenAccount: lmsrMarketTokenAccount,
+        platformTokenAccount: platformTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user2])
+      .rpc();
+
+    console.log("Buy shares transaction:", tx);
+
+    // 50 YES shares at b = 100 cost b * (ln(e^0.5 + 1) - ln 2) ≈ 28.09 tokens
+    const position = await program.account.position.fetch(lmsrPositionPDA);
+    expect(position.yesAmount.toNumber()).to.equal(50 * 10**6);
+
+    market = await program.account.market.fetch(lmsrMarketPDA);
+    expect(market.lmsr.yesShares.toNumber()).to.equal(50 * 10**6);
+  });
+
+  it("Creates a constant-product market, adds liquidity and swaps", async () => {
+    const closingTime = Math.floor(Date.now() / 1000) + 86400;
+
+    const [ammMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(2).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const ammMarketTokenAccount = await getAssociatedTokenAddress(testTokenMint, ammMarketPDA, true);
+
+    await program.methods
+      .createMarket(
+        "Will WIF hit $5 this week?",
+        "Constant-product test market",
+        new anchor.BN(closingTime),
+        testTokenMint,
+        "WIF",
+        "dogwifhat",
+        { key: { resolver: authority.publicKey } },
+        Array.from(Buffer.alloc(32, 1)),
+        "https://polymeme.app/markets/2/rules.json",
+        new anchor.BN(0),
+        { flat: {} },
+        new anchor.BN(0), // No creator seed
+        0,
+        new anchor.BN(0), // Global default bet limits
+        new anchor.BN(0),
+        new anchor.BN(0), // No exposure caps
+        0,
+        { open: {} },
+        Array.from(Buffer.alloc(32)),
+        new anchor.BN(0), // Claims never expire
+        { account: {} },
+        "", // No off-chain metadata document
+        Array.from(Buffer.alloc(32))
+      )
+      .accounts({
+        market: ammMarketPDA,
+        marketMetadata: metadataPDA(ammMarketPDA),
+        global: globalPDA,
+        creator: user1.publicKey,
+        creatorEntry: null, // Creation is permissionless
+        creatorPosition: null,
+        creatorTokenAccount: null,
+        marketTokenAccount: null,
+        requiredTokenMintAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    await createAccount(provider.connection, user1, testTokenMint, ammMarketPDA);
+
+    await program.methods
+      .initializeAmm(100) // 1% LP fee
+      .accounts({
+        market: ammMarketPDA,
+        creator: user1.publicKey,
+      })
+      .signers([user1])
+      .rpc();
+
+    const [liquidityPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("liquidity"), ammMarketPDA.toBuffer(), user1.publicKey.toBuffer()],
+      program.programId
+    );
+    const [lpPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), ammMarketPDA.toBuffer(), user1.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .addLiquidity(new anchor.BN(100 * 10**6))
+      .accounts({
+        liquidityPosition: liquidityPositionPDA,
+        position: lpPositionPDA,
+        market: ammMarketPDA,
+        user: user1.publicKey,
+        userTokenAccount: user1TokenAccount,
+        marketTokenAccount: ammMarketTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    const [traderPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), ammMarketPDA.toBuffer(), user2.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const tx = await program.methods
+      .swapCollateralForShares({ yes: {} }, new anchor.BN(10 * 10**6), new anchor.BN(0))
+      .accounts({
+        position: traderPositionPDA,
+        market: ammMarketPDA,
+        global: globalPDA,
+        user: user2.publicKey,
+        userTokenAccount: user2TokenAccount,
+        marketTokenAccount: ammMarketTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user2])
+      .rpc();
+
+    console.log("Swap transaction:", tx);
+
+    // 9.9 tokens after fee mint 9.9 sets; the pool keeps 100 * 100 / 109.9 YES
+    const position = await program.account.position.fetch(traderPositionPDA);
+    expect(position.yesAmount.toNumber()).to.equal(18908189);
+
+    const market = await program.account.market.fetch(ammMarketPDA);
+    expect(market.amm.lpSupply.toNumber()).to.equal(100 * 10**6);
+    expect(market.amm.yesReserve.toNumber()).to.equal(90991811 + 100000); // + fee sets
+    expect(market.amm.noReserve.toNumber()).to.equal(110000000);
+  });
+
+  it("Mints YES/NO outcome token pairs", async () => {
+    const [lmsrMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(1).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [yesMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("yes_mint"), lmsrMarketPDA.toBuffer()],
+      program.programId
+    );
+    const [noMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("no_mint"), lmsrMarketPDA.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createOutcomeMints()
+      .accounts({
+        market: lmsrMarketPDA,
+        yesMint,
+        noMint,
+        collateralMint: testTokenMint,
+        payer: user1.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user1])
+      .rpc();
+
+    const userYesAccount = await getAssociatedTokenAddress(yesMint, user1.publicKey);
+    const userNoAccount = await getAssociatedTokenAddress(noMint, user1.publicKey);
+
+    const tx = await program.methods
+      .mintOutcomeTokens(new anchor.BN(5 * 10**6))
+      .accounts({
+        market: lmsrMarketPDA,
+        yesMint,
+        noMint,
+        user: user1.publicKey,
+        userTokenAccount: user1TokenAccount,
+        marketTokenAccount: await getAssociatedTokenAddress(testTokenMint, lmsrMarketPDA, true),
+        userYesAccount,
+        userNoAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    console.log("Mint outcome tokens transaction:", tx);
+
+    const yesBalance = await provider.connection.getTokenAccountBalance(userYesAccount);
+    const noBalance = await provider.connection.getTokenAccountBalance(userNoAccount);
+    expect(yesBalance.value.amount).to.equal("5000000");
+    expect(noBalance.value.amount).to.equal("5000000");
+
+    const market = await program.account.market.fetch(lmsrMarketPDA);
+    expect(market.yesMint.toString()).to.equal(yesMint.toString());
+    expect(market.outcomeCollateral.toNumber()).to.equal(5 * 10**6);
+  });
+
+  it("Creates an order book and rests a YES bid", async () => {
+    const [lmsrMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(1).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [yesMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("yes_mint"), lmsrMarketPDA.toBuffer()],
+      program.programId
+    );
+    const [orderBookPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("order_book"), lmsrMarketPDA.toBuffer()],
+      program.programId
+    );
+    const [eventQueuePDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("event_queue"), lmsrMarketPDA.toBuffer()],
+      program.programId
+    );
+    const [openOrdersPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("open_orders"), lmsrMarketPDA.toBuffer(), user2.publicKey.toBuffer()],
+      program.programId
+    );
+    const baseVault = await getAssociatedTokenAddress(yesMint, orderBookPDA, true);
+    const quoteVault = await getAssociatedTokenAddress(testTokenMint, orderBookPDA, true);
+
+    await program.methods
+      .createOrderBook()
+      .accounts({
+        market: lmsrMarketPDA,
+        orderBook: orderBookPDA,
+        eventQueue: eventQueuePDA,
+        yesMint,
+        collateralMint: testTokenMint,
+        baseVault,
+        quoteVault,
+        payer: user1.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    const tx = await program.methods
+      .placeOrder({ bid: {} }, 4000, new anchor.BN(10 * 10**6), new anchor.BN(1))
+      .accounts({
+        market: lmsrMarketPDA,
+        orderBook: orderBookPDA,
+        eventQueue: eventQueuePDA,
+        openOrders: openOrdersPDA,
+        user: user2.publicKey,
+        yesMint,
+        userYesAccount: await getAssociatedTokenAddress(yesMint, user2.publicKey),
+        userTokenAccount: user2TokenAccount,
+        baseVault,
+        quoteVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user2])
+      .rpc();
+
+    console.log("Place order transaction:", tx);
+
+    // 10 YES @ 40% locks 4 tokens
+    const openOrders = await program.account.openOrders.fetch(openOrdersPDA);
+    expect(openOrders.quoteLocked.toNumber()).to.equal(4 * 10**6);
+
+    const orderBook = await program.account.orderBook.fetch(orderBookPDA);
+    expect(orderBook.bidCount).to.equal(1);
+    expect(orderBook.bids[0].priceBps).to.equal(4000);
+    expect(orderBook.bids[0].quantity.toNumber()).to.equal(10 * 10**6);
+  });
+
+  it("Creates a market seeded by the creator", async () => {
+    const closingTime = Math.floor(Date.now() / 1000) + 86400;
+    const seedAmount = 20 * 10**6; // 20 tokens
+
+    const [seededMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(3).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [creatorPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), seededMarketPDA.toBuffer(), user2.publicKey.toBuffer()],
+      program.programId
+    );
+    const seededMarketTokenAccount = await getAssociatedTokenAddress(testTokenMint, seededMarketPDA, true);
+
+    const tx = await program.methods
+      .createMarket(
+        "Will WIF hit $20 by end of 2024?",
+        "Seeded test market",
+        new anchor.BN(closingTime),
+        testTokenMint,
+        "WIF",
+        "dogwifhat",
+        { key: { resolver: authority.publicKey } },
+        Array.from(Buffer.alloc(32, 1)),
+        "https://polymeme.app/markets/3/rules.json",
+        new anchor.BN(0),
+        { flat: {} },
+        new anchor.BN(seedAmount),
+        2500, // 25% YES / 75% NO
+        new anchor.BN(0),
+        new anchor.BN(0),
+        new anchor.BN(0),
+        0,
+        { open: {} },
+        Array.from(Buffer.alloc(32)),
+        new anchor.BN(0), // Claims never expire
+        { account: {} },
+        "", // No off-chain metadata document
+        Array.from(Buffer.alloc(32))
+      )
+      .accounts({
+        market: seededMarketPDA,
+        marketMetadata: metadataPDA(seededMarketPDA),
+        global: globalPDA,
+        creator: user2.publicKey,
+        creatorEntry: null, // Creation is permissionless
+        creatorPosition: creatorPositionPDA,
+        creatorTokenAccount: user2TokenAccount,
+        marketTokenAccount: seededMarketTokenAccount,
+        requiredTokenMintAccount: testTokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user2])
+      .rpc();
+
+    console.log("Create seeded market transaction:", tx);
+
+    const market = await program.account.market.fetch(seededMarketPDA);
+    expect(market.seedAmount.toNumber()).to.equal(seedAmount);
+    expect(market.yesPool.toNumber()).to.equal(5 * 10**6);
+    expect(market.noPool.toNumber()).to.equal(15 * 10**6);
+    expect(market.yesBets.toNumber()).to.equal(0);
+
+    const position = await program.account.position.fetch(creatorPositionPDA);
+    expect(position.user.toString()).to.equal(user2.publicKey.toString());
+    expect(position.yesAmount.toNumber()).to.equal(5 * 10**6);
+    expect(position.noAmount.toNumber()).to.equal(15 * 10**6);
+  });
+
+  it("Rejects a creator position on an unseeded market", async () => {
+    const closingTime = Math.floor(Date.now() / 1000) + 86400;
+
+    const [unseededMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(4).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [creatorPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), unseededMarketPDA.toBuffer(), user2.publicKey.toBuffer()],
+      program.programId
+    );
+
+    let errorCode: string | undefined;
+    try {
+      await program.methods
+        .createMarket(
+          "Will BONK hit $1 by end of 2024?",
+          "Unseeded market with a stray creator position",
+          new anchor.BN(closingTime),
+          testTokenMint,
+          "BONK",
+          "Bonk",
+          { key: { resolver: authority.publicKey } },
+          Array.from(Buffer.alloc(32, 1)),
+          "https://polymeme.app/markets/4/rules.json",
+          new anchor.BN(0),
+          { flat: {} },
+          new anchor.BN(0), // No seed
+          5000,
+          new anchor.BN(0),
+          new anchor.BN(0),
+          new anchor.BN(0),
+          0,
+          { open: {} },
+          Array.from(Buffer.alloc(32)),
+          new anchor.BN(0),
+          { account: {} },
+          "",
+          Array.from(Buffer.alloc(32))
+        )
+        .accounts({
+          market: unseededMarketPDA,
+          marketMetadata: metadataPDA(unseededMarketPDA),
+          global: globalPDA,
+          creator: user2.publicKey,
+          creatorEntry: null,
+          creatorPosition: creatorPositionPDA, // Would be left open and empty
+          creatorTokenAccount: null,
+          marketTokenAccount: null,
+          requiredTokenMintAccount: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user2])
+        .rpc();
+    } catch (err) {
+      errorCode = (err as anchor.AnchorError).error?.errorCode?.code;
+    }
+
+    expect(errorCode).to.equal("UnexpectedSeedPosition");
+    expect(await provider.connection.getAccountInfo(creatorPositionPDA)).to.be.null;
+  });
+
+  it("Adds treasury-subsidized virtual liquidity", async () => {
+    const [seededMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(3).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .setSubsidyCap(new anchor.BN(100 * 10**6))
+      .accounts({
+        global: globalPDA,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    const tx = await program.methods
+      .setVirtualLiquidity(new anchor.BN(10 * 10**6))
+      .accounts({
+        market: seededMarketPDA,
+        global: globalPDA,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    console.log("Set virtual liquidity transaction:", tx);
+
+    const market = await program.account.market.fetch(seededMarketPDA);
+    expect(market.virtualLiquidity.toNumber()).to.equal(10 * 10**6);
+    expect(market.subsidySettled).to.equal(0);
+
+    // Twice the virtual liquidity is reserved against the cap
+    const globalState = await program.account.global.fetch(globalPDA);
+    expect(globalState.subsidyCommitted.toNumber()).to.equal(20 * 10**6);
+  });
+
+  it("Rejects a bet from a wallet below the holder gate", async () => {
+    const closingTime = Math.floor(Date.now() / 1000) + 86400;
+
+    // Bettors must hold 1,000 tokens of a separate community mint
+    const gateMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const user1GateAccount = await createAccount(provider.connection, user1, gateMint, user1.publicKey);
+    await mintTo(provider.connection, authority, gateMint, user1GateAccount, authority, 999 * 10**6);
+
+    const [gatedMarketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(4).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [gatedPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), gatedMarketPDA.toBuffer(), user1.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createMarket(
+        "Will BONK flip WIF by end of 2024?",
+        "Holder-gated test market",
+        new anchor.BN(closingTime),
+        testTokenMint,
+        "WIF",
+        "dogwifhat",
+        { key: { resolver: authority.publicKey } },
+        Array.from(Buffer.alloc(32, 1)),
+        "https://polymeme.app/markets/4/rules.json",
+        new anchor.BN(0),
+        { flat: {} },
+        new anchor.BN(0),
+        5000,
+        new anchor.BN(0),
+        new anchor.BN(0),
+        new anchor.BN(0),
+        0,
+        { tokenBalance: { mint: gateMint, minAmount: new anchor.BN(1000 * 10**6) } },
+        Array.from(Buffer.alloc(32)),
+        new anchor.BN(0),
+        { eventOnly: {} },
+        "",
+        Array.from(Buffer.alloc(32))
+      )
+      .accounts({
+        market: gatedMarketPDA,
+        marketMetadata: metadataPDA(gatedMarketPDA),
+        global: globalPDA,
+        creator: user2.publicKey,
+        creatorEntry: null,
+        creatorPosition: null,
+        creatorTokenAccount: null,
+        marketTokenAccount: null,
+        requiredTokenMintAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user2])
+      .rpc();
+
+    const gatedMarketTokenAccount = await createAccount(provider.connection, user1, testTokenMint, gatedMarketPDA);
+
+    let errorCode: string | undefined;
+    try {
+      await program.methods
+        .placeBet(new anchor.BN(10 * 10**6), { yes: {} }, new anchor.BN(0), [])
+        .accounts({
+          bet: null, // Event-only market
+          position: gatedPositionPDA,
+          market: gatedMarketPDA,
+          global: globalPDA,
+          user: user1.publicKey,
+          userTokenAccount: user1TokenAccount,
+          marketTokenAccount: gatedMarketTokenAccount,
+          platformTokenAccount: platformTokenAccount,
+          gateTokenAccount: user1GateAccount, // 999 tokens, one short of the gate
+          gateMetadata: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+    } catch (err) {
+      errorCode = (err as anchor.AnchorError).error?.errorCode?.code;
+    }
+
+    expect(errorCode).to.equal("HolderGateNotMet");
+    expect(await provider.connection.getAccountInfo(gatedPositionPDA)).to.be.null;
   });
-});
\ No newline at end of file
+});